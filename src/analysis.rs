@@ -0,0 +1,70 @@
+use crate::data::PInputData;
+use crate::score::{CarId, Time};
+use ahash::AHashMap;
+
+/// Time each car needs to reach the end of its path if it never waits.
+///
+/// Cars start at the end of their first street, so its length does not count.
+pub fn min_travel_times(input: &PInputData) -> Vec<Time> {
+    let street_length_of_name = input
+        .body
+        .streets
+        .iter()
+        .map(|street| (street.street_name.as_str(), street.street_length))
+        .collect::<AHashMap<&str, usize>>();
+    input
+        .body
+        .car_paths
+        .iter()
+        .map(|car_path| {
+            car_path
+                .street_names
+                .iter()
+                .skip(1)
+                .map(|street_name| street_length_of_name[street_name.as_str()])
+                .sum()
+        })
+        .collect()
+}
+
+/// Cars that cannot finish before the end of the simulation whatever the schedule.
+pub fn unreachable_cars(input: &PInputData) -> Vec<CarId> {
+    min_travel_times(input)
+        .iter()
+        .enumerate()
+        .filter(|(_, &min_travel_time)| min_travel_time > input.header.simulation_duration)
+        .map(|(car_id, _)| car_id)
+        .collect()
+}
+
+/// Number of car paths going through each street, indexed by street id.
+pub fn street_usage(input: &PInputData) -> Vec<usize> {
+    let street_id_of_name = input.street_id_of_name();
+    let mut usage = vec![0; input.body.streets.len()];
+    for car_path in &input.body.car_paths {
+        for street_name in &car_path.street_names {
+            usage[street_id_of_name[street_name.as_str()]] += 1;
+        }
+    }
+    usage
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{min_travel_times, unreachable_cars};
+    use crate::data::test_data;
+
+    #[test]
+    fn test_min_travel_times_example() {
+        let input_data = test_data::get_example_input_data();
+        assert_eq!(min_travel_times(&input_data), vec![6, 4]);
+    }
+
+    #[test]
+    fn test_unreachable_cars() {
+        let mut input_data = test_data::get_example_input_data();
+        assert!(unreachable_cars(&input_data).is_empty());
+        input_data.header.simulation_duration = 5;
+        assert_eq!(unreachable_cars(&input_data), vec![0]);
+    }
+}
@@ -0,0 +1,506 @@
+use crate::data::{PInputData, POutputData};
+use crate::score::{Action, CarTracker, ScoringRules, SimulationState, Simulator};
+use crate::writer::format_output;
+use anyhow::{bail, Context};
+use std::collections::VecDeque;
+use std::fmt::Write as FmtWrite;
+use std::fs::{read_to_string, rename, write};
+
+const MAGIC: &str = "ghc2021-checkpoint 1";
+
+/// Simulation state and the fingerprint of the input header, schedule and rules it was
+/// simulated with.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Checkpoint {
+    pub(crate) fingerprint: u64,
+    pub(crate) state: SimulationState,
+}
+
+/// FNV-1a hash of the input header (simulation duration, counts and bonus), the output
+/// file content and the scoring rules, stable across builds.
+pub(crate) fn fingerprint(input: &PInputData, output: &POutputData, rules: &ScoringRules) -> u64 {
+    let content = format!("{:?}{}{:?}", input.header, format_output(output), rules);
+    content.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Text format, whitespace separated:
+///
+/// ```text
+/// ghc2021-checkpoint 1 <fingerprint, 16 hexadecimal digits>
+/// <time> <score> <finished cars> <stranded cars> <car trackers> <streets> <active streets>
+/// <car id> <distance on current street> <index in car path> <action> (one line per car tracker)
+/// <street id> <queue length> <car tracker index>...                  (one line per active street)
+/// ```
+///
+/// Active streets are the streets with a non empty queue, in the order they are simulated.
+///
+/// Actions are `w<street id>` (waiting), `d<street id>:<street length>` (driving) and
+/// `f<time>` (finished), the next ones are read from the car path.
+pub fn format_checkpoint(checkpoint: &Checkpoint) -> String {
+    let state = &checkpoint.state;
+    let mut s = String::new();
+    writeln!(s, "{} {:016x}", MAGIC, checkpoint.fingerprint).unwrap();
+    writeln!(
+        s,
+        "{} {} {} {} {} {} {}",
+        state.time,
+        state.score,
+        state.finished_cars,
+        state.stranded_cars,
+        state.car_trackers.len(),
+        state.street_queues.len(),
+        state.active_streets.len()
+    )
+    .unwrap();
+    for car_tracker in &state.car_trackers {
+        write!(
+            s,
+            "{} {} {} ",
+            car_tracker.id, car_tracker.distance_current_street, car_tracker.street_index
+        )
+        .unwrap();
+        match car_tracker.action {
+            Action::Waiting(street_id) => writeln!(s, "w{}", street_id),
+            Action::Driving(street_id, street_length) => {
+                writeln!(s, "d{}:{}", street_id, street_length)
+            }
+            Action::Finished(time) => writeln!(s, "f{}", time),
+        }
+        .unwrap();
+    }
+    for &street_id in &state.active_streets {
+        let street_queue = &state.street_queues[street_id];
+        write!(s, "{} {}", street_id, street_queue.len()).unwrap();
+        for tracker_index in street_queue {
+            write!(s, " {}", tracker_index).unwrap();
+        }
+        writeln!(s).unwrap();
+    }
+    s
+}
+
+fn next_number<'a>(
+    tokens: &mut impl Iterator<Item = &'a str>,
+    what: &str,
+) -> anyhow::Result<usize> {
+    let token = tokens.next().with_context(|| format!("missing {}", what))?;
+    token
+        .parse()
+        .with_context(|| format!("invalid {}: {}", what, token))
+}
+
+fn parse_action(token: &str) -> anyhow::Result<Action> {
+    let number = |s: &str| {
+        s.parse::<usize>()
+            .with_context(|| format!("invalid action: {}", token))
+    };
+    match token.split_at(token.len().min(1)) {
+        ("w", street_id) => Ok(Action::Waiting(number(street_id)?)),
+        ("d", driving) => match driving.split_once(':') {
+            Some((street_id, street_length)) => {
+                Ok(Action::Driving(number(street_id)?, number(street_length)?))
+            }
+            None => bail!("invalid action: {}", token),
+        },
+        ("f", time) => Ok(Action::Finished(number(time)?)),
+        _ => bail!("invalid action: {}", token),
+    }
+}
+
+/// Counts are checked against `input` before anything is allocated.
+pub fn parse_checkpoint(s: &str, input: &PInputData) -> anyhow::Result<Checkpoint> {
+    let content = match s.strip_prefix(MAGIC) {
+        Some(content) => content,
+        None => bail!("not a checkpoint: expected {:?} header", MAGIC),
+    };
+    let mut tokens = content.split_ascii_whitespace();
+    let token = tokens.next().context("missing fingerprint")?;
+    let fingerprint = u64::from_str_radix(token, 16)
+        .with_context(|| format!("invalid fingerprint: {}", token))?;
+    let time = next_number(&mut tokens, "time")?;
+    let score = next_number(&mut tokens, "score")?;
+    let finished_cars = next_number(&mut tokens, "finished cars")?;
+    let stranded_cars = next_number(&mut tokens, "stranded cars")?;
+    let car_tracker_count = next_number(&mut tokens, "car tracker count")?;
+    let street_count = next_number(&mut tokens, "street count")?;
+    let active_street_count = next_number(&mut tokens, "active street count")?;
+    let cars = input.body.car_paths.len();
+    let streets = input.body.streets.len();
+    if car_tracker_count != cars {
+        bail!(
+            "checkpoint has {} cars, input file has {}",
+            car_tracker_count,
+            cars
+        );
+    }
+    if street_count != streets {
+        bail!(
+            "checkpoint has {} streets, input file has {}",
+            street_count,
+            streets
+        );
+    }
+    if active_street_count > street_count {
+        bail!(
+            "{} active streets out of {} streets",
+            active_street_count,
+            street_count
+        );
+    }
+    let mut car_trackers = Vec::with_capacity(car_tracker_count);
+    for _ in 0..car_tracker_count {
+        let id = next_number(&mut tokens, "car id")?;
+        let distance_current_street = next_number(&mut tokens, "distance")?;
+        let street_index = next_number(&mut tokens, "index in car path")?;
+        let token = tokens
+            .next()
+            .with_context(|| format!("missing action of car {}", id))?;
+        car_trackers.push(CarTracker {
+            id,
+            street_index,
+            action: parse_action(token)?,
+            distance_current_street,
+        });
+    }
+    let mut street_queues = vec![VecDeque::new(); street_count];
+    let mut active_streets = Vec::with_capacity(active_street_count);
+    for _ in 0..active_street_count {
+        let street_id = next_number(&mut tokens, "street id")?;
+        if street_id >= street_count {
+            bail!("unknown street {}", street_id);
+        }
+        if !street_queues[street_id].is_empty() {
+            bail!("street {}: queue listed more than once", street_id);
+        }
+        let queue_length = next_number(&mut tokens, "queue length")?;
+        if queue_length == 0 {
+            bail!("street {}: active street with an empty queue", street_id);
+        }
+        let street_queue = &mut street_queues[street_id];
+        for _ in 0..queue_length {
+            let tracker_index = next_number(&mut tokens, "car tracker index")?;
+            if tracker_index >= car_trackers.len() {
+                bail!(
+                    "street {}: unknown car tracker index {}",
+                    street_id,
+                    tracker_index
+                );
+            }
+            street_queue.push_back(tracker_index);
+        }
+        active_streets.push(street_id);
+    }
+    if let Some(token) = tokens.next() {
+        bail!("unexpected content after the last street queue: {}", token);
+    }
+    Ok(Checkpoint {
+        fingerprint,
+        state: SimulationState {
+            time,
+            score,
+            finished_cars,
+            stranded_cars,
+            car_trackers,
+            street_queues,
+            active_streets,
+        },
+    })
+}
+
+/// Checks that `checkpoint` can be resumed with `input`, `output` and `rules`, `simulator`
+/// being built from `input`.
+///
+/// Car trackers follow the car paths, every waiting car is queued exactly once and the
+/// finished and stranded counts match the trackers.
+pub fn check_checkpoint(
+    simulator: &Simulator,
+    input: &PInputData,
+    output: &POutputData,
+    rules: &ScoringRules,
+    checkpoint: &Checkpoint,
+) -> anyhow::Result<()> {
+    if checkpoint.fingerprint != fingerprint(input, output, rules) {
+        bail!("checkpoint was saved with another input file, output file or other scoring rules");
+    }
+    let street_id_of_name = input.street_id_of_name();
+    let state = &checkpoint.state;
+    for (tracker_index, car_tracker) in state.car_trackers.iter().enumerate() {
+        if car_tracker.id != tracker_index {
+            bail!("car {} listed as car {}", tracker_index, car_tracker.id);
+        }
+        let car_path = &input.body.car_paths[car_tracker.id].street_names;
+        let street_id = match car_path.get(car_tracker.street_index) {
+            Some(street_name) => street_id_of_name[street_name.as_str()],
+            None => bail!(
+                "car {}: street {} of a path of {} streets",
+                car_tracker.id,
+                car_tracker.street_index,
+                car_path.len()
+            ),
+        };
+        let consistent = match car_tracker.action {
+            // no light at the end of the last street
+            Action::Waiting(action_street_id) => {
+                action_street_id == street_id && car_tracker.street_index + 1 < car_path.len()
+            }
+            Action::Driving(action_street_id, street_length) => {
+                action_street_id == street_id
+                    && street_length == input.body.streets[street_id].street_length
+            }
+            Action::Finished(_) => car_tracker.street_index + 1 == car_path.len(),
+        };
+        if !consistent {
+            bail!(
+                "car {}: {:?} on street {} of its path",
+                car_tracker.id,
+                car_tracker.action,
+                car_tracker.street_index
+            );
+        }
+    }
+    let mut queued = vec![false; state.car_trackers.len()];
+    for &street_id in &state.active_streets {
+        for &tracker_index in &state.street_queues[street_id] {
+            let car_tracker = &state.car_trackers[tracker_index];
+            if car_tracker.action != Action::Waiting(street_id) {
+                bail!(
+                    "car {} queued on street {} but {:?}",
+                    car_tracker.id,
+                    street_id,
+                    car_tracker.action
+                );
+            }
+            if queued[tracker_index] {
+                bail!("car {} queued more than once", car_tracker.id);
+            }
+            queued[tracker_index] = true;
+        }
+    }
+    let light_schedules = simulator.light_schedules(output);
+    let mut finished_cars = 0;
+    let mut stranded_cars = 0;
+    for car_tracker in &state.car_trackers {
+        match car_tracker.action {
+            Action::Waiting(street_id) => {
+                if !queued[car_tracker.id] {
+                    bail!(
+                        "car {} waiting on street {} but not queued",
+                        car_tracker.id,
+                        street_id
+                    );
+                }
+                if light_schedules.never_green(street_id) {
+                    stranded_cars += 1;
+                }
+            }
+            Action::Finished(_) => finished_cars += 1,
+            Action::Driving(..) => {}
+        }
+    }
+    if state.finished_cars != finished_cars {
+        bail!(
+            "{} finished cars, {} car trackers are finished",
+            state.finished_cars,
+            finished_cars
+        );
+    }
+    if state.stranded_cars != stranded_cars {
+        bail!(
+            "{} stranded cars, {} car trackers wait on never green streets",
+            state.stranded_cars,
+            stranded_cars
+        );
+    }
+    Ok(())
+}
+
+/// Written to a temporary file first so that a crash never leaves a truncated checkpoint.
+pub fn save_checkpoint(path: &str, checkpoint: &Checkpoint) -> anyhow::Result<()> {
+    let tmp_path = format!("{}.tmp", path);
+    write(&tmp_path, format_checkpoint(checkpoint))?;
+    rename(&tmp_path, path)?;
+    Ok(())
+}
+
+pub fn load_checkpoint(path: &str, input: &PInputData) -> anyhow::Result<Checkpoint> {
+    let content = read_to_string(path)?;
+    parse_checkpoint(&content, input).with_context(|| format!("invalid checkpoint {}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checkpoint::{
+        check_checkpoint, fingerprint, format_checkpoint, parse_checkpoint, Checkpoint,
+    };
+    use crate::data::{test_data, POutputData};
+    use crate::score::{compute_score, ScoringRules, Simulator};
+
+    #[test]
+    fn test_split_run_example() {
+        let mut input_data = test_data::get_example_input_data();
+        input_data.header.simulation_duration = 10;
+        let output_data = test_data::get_example_output_data();
+        let simulator = Simulator::new(&input_data);
+        let score = compute_score(&input_data, &output_data);
+        for split_time in 0..=10 {
+            let mut state = simulator.initial_state(&output_data, &mut ());
+            simulator.resume(&output_data, &mut state, split_time, &mut ());
+            let checkpoint = Checkpoint {
+                fingerprint: fingerprint(&input_data, &output_data, &ScoringRules::default()),
+                state,
+            };
+            let saved = format_checkpoint(&checkpoint);
+            let mut restored = parse_checkpoint(&saved, &input_data).unwrap();
+            assert_eq!(restored, checkpoint);
+            simulator.resume(&output_data, &mut restored.state, 10, &mut ());
+            assert_eq!(restored.state.score, score, "split at {}", split_time);
+        }
+    }
+
+    #[test]
+    fn test_check_checkpoint_fingerprint() {
+        let input_data = test_data::get_example_input_data();
+        let output_data = test_data::get_example_output_data();
+        let rules = ScoringRules::default();
+        let simulator = Simulator::new(&input_data);
+        let checkpoint = Checkpoint {
+            fingerprint: fingerprint(&input_data, &output_data, &rules),
+            state: simulator.initial_state(&output_data, &mut ()),
+        };
+        assert!(
+            check_checkpoint(&simulator, &input_data, &output_data, &rules, &checkpoint).is_ok()
+        );
+        let mut other_output_data = output_data.clone();
+        other_output_data.intersection_schedules[0].light_schedules[0].1 = 1;
+        assert!(check_checkpoint(
+            &simulator,
+            &input_data,
+            &other_output_data,
+            &rules,
+            &checkpoint
+        )
+        .is_err());
+        let other_rules = ScoringRules {
+            crossing_delay: 1,
+            ..rules
+        };
+        assert!(check_checkpoint(
+            &simulator,
+            &input_data,
+            &output_data,
+            &other_rules,
+            &checkpoint
+        )
+        .is_err());
+        // same schedule, another bonus
+        let mut other_input_data = test_data::get_example_input_data();
+        other_input_data.header.bonus = 5;
+        assert!(check_checkpoint(
+            &Simulator::new(&other_input_data),
+            &other_input_data,
+            &output_data,
+            &rules,
+            &checkpoint
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_checkpoint_errors() {
+        let input_data = test_data::get_example_input_data();
+        let parse = |s: &str| parse_checkpoint(s, &input_data);
+        // cars waiting at the end of their first street
+        let cars = "0 0 0 w0\n1 0 0 w2\n";
+        let queues = "0 1 0\n2 1 1\n";
+        assert!(parse(&format!("0 0 0 0 2 5 2\n{}{}", cars, queues)).is_err());
+        let header = "ghc2021-checkpoint 1 0\n";
+        assert!(parse(&format!("{}0 0 0 0 2 5 2\n{}{}", header, cars, queues)).is_ok());
+        assert!(parse(&format!("{}0 0 0 0 2 5 2\n0 0 0 x3\n", header)).is_err());
+        // counts are checked before allocating
+        assert!(parse(&format!("{}0 0 0 0 2 999999999999 0\n{}", header, cars)).is_err());
+        assert!(parse(&format!("{}0 0 0 0 999999999999 5 0\n", header)).is_err());
+        // queue refers to a missing car tracker
+        assert!(parse(&format!("{}0 0 0 0 2 5 1\n{}0 1 2\n", header, cars)).is_err());
+        // unknown street
+        assert!(parse(&format!("{}0 0 0 0 2 5 1\n{}5 1 0\n", header, cars)).is_err());
+    }
+
+    // checkpoint with the given "<finished cars> <stranded cars>", car and queue lines
+    fn check(
+        output_data: &POutputData,
+        counts: &str,
+        cars: &str,
+        queues: &str,
+    ) -> anyhow::Result<()> {
+        let input_data = test_data::get_example_input_data();
+        let rules = ScoringRules::default();
+        let checkpoint = parse_checkpoint(
+            &format!(
+                "ghc2021-checkpoint 1 {:016x}\n0 0 {} 2 5 {}\n{}{}",
+                fingerprint(&input_data, output_data, &rules),
+                counts,
+                queues.lines().count(),
+                cars,
+                queues
+            ),
+            &input_data,
+        )
+        .unwrap();
+        check_checkpoint(
+            &Simulator::new(&input_data),
+            &input_data,
+            output_data,
+            &rules,
+            &checkpoint,
+        )
+    }
+
+    #[test]
+    fn test_check_checkpoint_paths() {
+        let output_data = test_data::get_example_output_data();
+        let check = |cars: &str, queues: &str| check(&output_data, "0 0", cars, queues);
+        assert!(check("0 0 0 w0\n1 0 0 w2\n", "0 1 0\n2 1 1\n").is_ok());
+        // rue-d-amsterdam is not the first street of car 0
+        assert!(check("0 0 0 w1\n1 0 0 w2\n", "1 1 0\n2 1 1\n").is_err());
+        // no light at the end of rue-de-londres, the last street of car 1
+        assert!(check("0 0 0 w0\n1 0 2 w0\n", "0 2 0 1\n").is_err());
+        // car 1 is queued but driving
+        assert!(check("0 0 0 w0\n1 0 0 d2:1\n", "0 1 0\n2 1 1\n").is_err());
+        // car trackers out of order
+        assert!(check("1 0 0 w2\n0 0 0 w0\n", "0 1 1\n2 1 0\n").is_err());
+    }
+
+    #[test]
+    fn test_check_checkpoint_queues() {
+        let output_data = test_data::get_example_output_data();
+        let check = |cars: &str, queues: &str| check(&output_data, "0 0", cars, queues);
+        let cars = "0 0 0 w0\n1 0 0 w2\n";
+        assert!(check(cars, "0 1 0\n2 1 1\n").is_ok());
+        // car 0 queued twice
+        assert!(check(cars, "0 2 0 0\n2 1 1\n").is_err());
+        // car 1 waiting but not queued
+        assert!(check(cars, "0 1 0\n").is_err());
+    }
+
+    #[test]
+    fn test_check_checkpoint_counts() {
+        let mut output_data = test_data::get_example_output_data();
+        // car 1 finished, car 0 waiting at the end of its first street
+        let cars = "0 0 0 w0\n1 0 2 f5\n";
+        let queues = "0 1 0\n";
+        assert!(check(&output_data, "1 0", cars, queues).is_ok());
+        assert!(check(&output_data, "0 0", cars, queues).is_err());
+        assert!(check(&output_data, "1 1", cars, queues).is_err());
+        // both cars waiting on rue-de-moscou
+        let cars = "0 0 2 w4\n1 0 1 w4\n";
+        let queues = "4 2 0 1\n";
+        assert!(check(&output_data, "0 0", cars, queues).is_ok());
+        assert!(check(&output_data, "0 2", cars, queues).is_err());
+        // rue-de-moscou is never green, both cars are stranded
+        output_data.intersection_schedules[2].light_schedules[0].1 = 0;
+        assert!(check(&output_data, "0 2", cars, queues).is_ok());
+        assert!(check(&output_data, "0 0", cars, queues).is_err());
+    }
+}
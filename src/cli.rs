@@ -1,10 +1,15 @@
-use clap::{App, Arg};
+use crate::generator::STRATEGIES;
+use crate::graph::GRAPH_FORMATS;
+use crate::optimizer::METHODS;
+use crate::score::{DEADLINES, START_POSITIONS};
+use clap::{App, AppSettings, Arg, ArgGroup, SubCommand};
 
 pub fn get_app() -> App<'static, 'static> {
     App::new(crate_name!())
         .version(crate_version!())
-        .author(crate_authors!("\n"))
+        .author(env!("CARGO_PKG_AUTHORS"))
         .about("Google Hashcode Score Calculator")
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(
             Arg::with_name("input")
                 .help("input file paths")
@@ -20,4 +25,595 @@ pub fn get_app() -> App<'static, 'static> {
                 .required(true)
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("watch")
+                .short("w")
+                .long("watch")
+                .help("keep running and re-score output files whenever they change"),
+        )
+        .arg(
+            Arg::with_name("interval")
+                .long("interval")
+                .help("polling interval in milliseconds (watch mode)")
+                .default_value("500")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("crossing_delay")
+                .long("crossing-delay")
+                .help("seconds needed to cross an intersection (scoring rule variant)")
+                .default_value("0")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("start")
+                .long("start")
+                .help("where cars start on their first street (scoring rule variant)")
+                .possible_values(START_POSITIONS)
+                .default_value("end")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("deadline")
+                .long("deadline")
+                .help("whether cars finishing at the end of the simulation score (scoring rule variant)")
+                .possible_values(DEADLINES)
+                .default_value("inclusive")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("bonus_multiplier")
+                .long("bonus-multiplier")
+                .help("bonus multiplier (scoring rule variant)")
+                .default_value("1")
+                .takes_value(true),
+        )
+        .subcommand(
+            SubCommand::with_name("diff")
+                .about("Compare two output files on the same input file")
+                .arg(
+                    Arg::with_name("input")
+                        .help("input file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("output_a")
+                        .help("first output file path (A)")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("output_b")
+                        .help("second output file path (B)")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("limit")
+                        .long("limit")
+                        .help("maximum number of cars and intersections listed")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("generate")
+                .about("Generate a baseline output file from traffic counts")
+                .arg(
+                    Arg::with_name("input")
+                        .help("input file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .help("output file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("strategy")
+                        .long("strategy")
+                        .help("green light durations strategy")
+                        .possible_values(STRATEGIES)
+                        .default_value("proportional")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("optimize")
+                .about("Improve an output file with local search")
+                .arg(
+                    Arg::with_name("input")
+                        .help("input file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .help("output file path, rewritten after each improvement")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .help("initial output file path (default: generated baseline)")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("strategy")
+                        .long("strategy")
+                        .help("baseline strategy when no initial output file is provided")
+                        .possible_values(STRATEGIES)
+                        .default_value("proportional")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("method")
+                        .long("method")
+                        .help("local search method")
+                        .possible_values(METHODS)
+                        .default_value("hill-climbing")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("time")
+                        .long("time")
+                        .help("time budget in seconds")
+                        .default_value("60")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .help("random seed")
+                        .default_value("0")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("temperature")
+                        .long("temperature")
+                        .help("initial annealing temperature, in points")
+                        .default_value("100")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("skip_unreachable")
+                        .long("skip-unreachable")
+                        .help(
+                        "do not simulate cars that cannot finish (faster, scores are optimistic)",
+                    ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("reorder")
+                .about("Reorder intersection cycles by simulated first arrival, keeping durations")
+                .arg(
+                    Arg::with_name("input")
+                        .help("input file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .help("output file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("reordered")
+                        .short("o")
+                        .help("reordered output file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("passes")
+                        .long("passes")
+                        .help("maximum number of simulate and reorder passes")
+                        .default_value("10")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("normalize")
+                .about("Shrink an output file without lowering its score")
+                .arg(
+                    Arg::with_name("input")
+                        .help("input file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .help("output file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("normalized")
+                        .short("o")
+                        .help("normalized output file path")
+                        .required(true)
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("stats")
+                .about("Print input file statistics")
+                .arg(
+                    Arg::with_name("input")
+                        .help("input file paths")
+                        .multiple(true)
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("print one JSON object per input file"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("explain")
+                .about("Print the timeline of one car")
+                .arg(
+                    Arg::with_name("input")
+                        .help("input file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .help("output file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("car")
+                        .long("car")
+                        .help("car id (0-based, in input file order)")
+                        .required(true)
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("graph")
+                .about("Export the city graph (intersections and streets)")
+                .arg(
+                    Arg::with_name("input")
+                        .help("input file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("graph")
+                        .short("o")
+                        .help("graph file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("submission")
+                        .long("submission")
+                        .help("output file path, edges are weighted by simulated waiting time")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .help("graph file format")
+                        .possible_values(GRAPH_FORMATS)
+                        .default_value("dot")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("heatmap")
+                .about("Write an HTML report with SVG charts of a simulated submission")
+                .arg(
+                    Arg::with_name("input")
+                        .help("input file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .help("output file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("report")
+                        .short("o")
+                        .help("HTML report file path")
+                        .required(true)
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("sweep")
+                .about("Score an output file for several simulation durations")
+                .arg(
+                    Arg::with_name("input")
+                        .help("input file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .help("output file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("durations")
+                        .long("durations")
+                        .help("comma separated simulation durations in seconds")
+                        .required(true)
+                        .use_delimiter(true)
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("sensitivity")
+                .about("Rank the score change of one more or one less second of green per street")
+                .arg(
+                    Arg::with_name("input")
+                        .help("input file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .help("output file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("threads")
+                        .long("threads")
+                        .help("number of threads (default: available parallelism)")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("limit")
+                        .long("limit")
+                        .help("print only the first changes")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("impact")
+                .about("Rank cars by how much the others gain when they are removed")
+                .arg(
+                    Arg::with_name("input")
+                        .help("input file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .help("output file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("top")
+                        .long("top")
+                        .help("only evaluate the cars waiting the most (default: all cars)")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("time")
+                        .long("time")
+                        .help("time budget in seconds")
+                        .default_value("60")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("threads")
+                        .long("threads")
+                        .help("number of threads (default: available parallelism)")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("limit")
+                        .long("limit")
+                        .help("print only the first cars")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("rank")
+                .about("Score many output files for the same input file and rank them")
+                .arg(
+                    Arg::with_name("input")
+                        .help("input file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("outputs")
+                        .help("output file paths")
+                        .multiple(true)
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("threads")
+                        .long("threads")
+                        .help("number of threads (default: available parallelism)")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("copy_best")
+                        .long("copy-best")
+                        .help("copy the best output file to this path")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("merge")
+                .about("Merge output files for the same input file intersection by intersection")
+                .arg(
+                    Arg::with_name("input")
+                        .help("input file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("outputs")
+                        .help("output file paths")
+                        .multiple(true)
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("merged")
+                        .short("o")
+                        .help("merged output file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("log")
+                        .long("log")
+                        .help("file listing the source of each intersection schedule (default: <merged>.log)")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("checkpoint")
+                .about("Simulate up to a given time and save the simulation state")
+                .arg(
+                    Arg::with_name("input")
+                        .help("input file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .help("output file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("at")
+                        .long("at")
+                        .help("time to stop at, in seconds")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("checkpoint")
+                        .short("o")
+                        .help("checkpoint file path")
+                        .required(true)
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("resume")
+                .about("Resume a simulation from a checkpoint and print the final score")
+                .arg(
+                    Arg::with_name("input")
+                        .help("input file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .help("output file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("checkpoint")
+                        .help("checkpoint file path")
+                        .required(true)
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("query")
+                .about("Print where cars are and which cars wait at a light at a given time")
+                .arg(
+                    Arg::with_name("input")
+                        .help("input file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .help("output file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("time")
+                        .long("time")
+                        .short("t")
+                        .help("time in seconds, before lights let cars through")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("car")
+                        .long("car")
+                        .help("car ids (0-based, in input file order)")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("street")
+                        .long("street")
+                        .help("street names")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .takes_value(true),
+                )
+                .group(
+                    ArgGroup::with_name("target")
+                        .args(&["car", "street"])
+                        .multiple(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("timeline")
+                .about("Export the score accumulated over the simulation as CSV")
+                .arg(
+                    Arg::with_name("input")
+                        .help("input file path")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .help("output file paths, one pair of columns each")
+                        .required(true)
+                        .multiple(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("bucket")
+                        .long("bucket")
+                        .short("k")
+                        .help("one row every K seconds")
+                        .default_value("1")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("csv")
+                        .long("csv")
+                        .short("o")
+                        .help("CSV file path, standard output by default")
+                        .takes_value(true),
+                ),
+        )
 }
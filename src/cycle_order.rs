@@ -0,0 +1,103 @@
+use crate::data::{PInputData, PIntersectionSchedule, POutputData};
+use crate::score::{CarId, Observer, Score, Simulator, StreetId, Time};
+use ahash::AHashMap;
+use log::info;
+
+struct FirstArrivalObserver {
+    first_arrival: Vec<Option<Time>>,
+}
+
+impl Observer for FirstArrivalObserver {
+    fn on_queued(&mut self, time: Time, street_id: StreetId, _car_id: CarId, _cars_ahead: usize) {
+        let first_arrival = &mut self.first_arrival[street_id];
+        if first_arrival.is_none() {
+            *first_arrival = Some(time);
+        }
+    }
+}
+
+// streets never reached keep their relative order, after the others
+fn reorder(
+    intersection_schedule: &PIntersectionSchedule,
+    street_id_of_name: &AHashMap<&str, StreetId>,
+    first_arrival: &[Option<Time>],
+) -> PIntersectionSchedule {
+    let mut light_schedules = intersection_schedule.light_schedules.clone();
+    light_schedules.sort_by_key(|(street_name, _)| {
+        street_id_of_name
+            .get(street_name.as_str())
+            .and_then(|&street_id| first_arrival[street_id])
+            .unwrap_or(Time::MAX)
+    });
+    PIntersectionSchedule {
+        intersection_id: intersection_schedule.intersection_id,
+        incoming_streets: intersection_schedule.incoming_streets,
+        light_schedules,
+    }
+}
+
+/// Reorders each intersection cycle by simulated first arrival, keeping durations.
+///
+/// Reordering changes arrival times downstream, so it is repeated up to `passes` times
+/// while the score improves. Returns the best schedule and its score.
+pub fn reorder_cycles(
+    input: &PInputData,
+    output: &POutputData,
+    passes: usize,
+) -> (POutputData, Score) {
+    let simulator = Simulator::new(input);
+    let street_id_of_name = input.street_id_of_name();
+
+    let mut observer = FirstArrivalObserver {
+        first_arrival: vec![None; input.body.streets.len()],
+    };
+    let mut best_score = simulator.simulate(output, &mut observer);
+    let mut best = output.clone();
+    for pass in 0..passes {
+        let candidate = POutputData {
+            schedules: best.schedules,
+            intersection_schedules: best
+                .intersection_schedules
+                .iter()
+                .map(|schedule| reorder(schedule, &street_id_of_name, &observer.first_arrival))
+                .collect(),
+        };
+        if candidate == best {
+            break;
+        }
+        observer.first_arrival.iter_mut().for_each(|t| *t = None);
+        let score = simulator.simulate(&candidate, &mut observer);
+        info!("pass {}: score {}", pass, score);
+        if score <= best_score {
+            break;
+        }
+        best_score = score;
+        best = candidate;
+    }
+    (best, best_score)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cycle_order::reorder_cycles;
+    use crate::data::test_data;
+    use crate::generator::{generate, Strategy};
+    use crate::score::compute_score;
+
+    #[test]
+    fn test_reorder_cycles_example() {
+        let input_data = test_data::get_example_input_data();
+        let output_data = generate(&input_data, Strategy::Uniform);
+        assert_eq!(compute_score(&input_data, &output_data), 1001);
+        let (reordered, score) = reorder_cycles(&input_data, &output_data, 10);
+        assert_eq!(score, 2002);
+        // car 1 is at the end of rue-d-athenes at time 0, car 0 reaches rue-d-amsterdam at time 1
+        assert_eq!(
+            reordered.intersection_schedules[1].light_schedules,
+            vec![
+                ("rue-d-athenes".to_string(), 1),
+                ("rue-d-amsterdam".to_string(), 1)
+            ]
+        );
+    }
+}
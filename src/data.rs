@@ -1,3 +1,5 @@
+use ahash::AHashMap;
+
 #[derive(Debug, PartialEq)]
 pub struct InputHeader {
     pub(crate) simulation_duration: usize,
@@ -38,8 +40,20 @@ pub struct PInputData {
     pub(crate) body: PInputBody,
 }
 
+impl PInputData {
+    /// Street id of each street name, street ids being indexes in `body.streets`.
+    pub(crate) fn street_id_of_name(&self) -> AHashMap<&str, usize> {
+        self.body
+            .streets
+            .iter()
+            .enumerate()
+            .map(|(street_id, street)| (street.street_name.as_str(), street_id))
+            .collect()
+    }
+}
+
 // For parser only
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct PIntersectionSchedule {
     pub(crate) intersection_id: usize,
     pub(crate) incoming_streets: usize,
@@ -47,12 +61,24 @@ pub struct PIntersectionSchedule {
 }
 
 // For parser only
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct POutputData {
     pub(crate) schedules: usize,
     pub(crate) intersection_schedules: Vec<PIntersectionSchedule>,
 }
 
+impl POutputData {
+    /// Submission with the intersection schedules that are not `None`, in order.
+    pub(crate) fn from_schedules(schedules: &[Option<PIntersectionSchedule>]) -> Self {
+        let intersection_schedules: Vec<PIntersectionSchedule> =
+            schedules.iter().flatten().cloned().collect();
+        POutputData {
+            schedules: intersection_schedules.len(),
+            intersection_schedules,
+        }
+    }
+}
+
 #[cfg(test)]
 pub(crate) mod test_data {
     use crate::data::{
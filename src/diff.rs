@@ -0,0 +1,247 @@
+use crate::data::{PInputData, PIntersectionSchedule, POutputData};
+use crate::report::{format_signed, simulate_report, StreetStats};
+use crate::score::{CarId, Score, Time};
+use ahash::AHashMap;
+use num_format::{Locale, ToFormattedString};
+use std::cmp::Reverse;
+
+type IntersectionId = usize;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum ScheduleChange {
+    Unchanged,
+    Changed,
+    OnlyA,
+    OnlyB,
+    Unscheduled,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct CarDiff {
+    pub(crate) car_id: CarId,
+    pub(crate) finish_time_a: Option<Time>,
+    pub(crate) finish_time_b: Option<Time>,
+    // points of b minus points of a
+    pub(crate) delta: i64,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct IntersectionDiff {
+    pub(crate) intersection_id: IntersectionId,
+    pub(crate) schedule_change: ScheduleChange,
+    pub(crate) stats_a: StreetStats,
+    pub(crate) stats_b: StreetStats,
+    // waiting time of a minus waiting time of b at this intersection, not a score
+    // contribution: a car that waits less may still miss the deadline
+    pub(crate) waiting_time_delta: i64,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct SubmissionDiff {
+    pub(crate) score_a: Score,
+    pub(crate) score_b: Score,
+    // only cars whose points differ, biggest delta first
+    pub(crate) cars: Vec<CarDiff>,
+    // only intersections whose schedule or statistics differ, biggest waiting time delta first
+    pub(crate) intersections: Vec<IntersectionDiff>,
+}
+
+fn intersection_stats(input: &PInputData, street_stats: &[StreetStats]) -> Vec<StreetStats> {
+    let mut intersections = vec![StreetStats::default(); input.header.intersections];
+    for (street, stats) in input.body.streets.iter().zip(street_stats) {
+        let intersection = &mut intersections[street.intersection_end];
+        intersection.cars_passed += stats.cars_passed;
+        intersection.waiting_time += stats.waiting_time;
+        intersection.max_queue_length = intersection.max_queue_length.max(stats.max_queue_length);
+    }
+    intersections
+}
+
+fn schedules_by_intersection(
+    output: &POutputData,
+) -> AHashMap<IntersectionId, &PIntersectionSchedule> {
+    output
+        .intersection_schedules
+        .iter()
+        .map(|schedule| (schedule.intersection_id, schedule))
+        .collect()
+}
+
+pub(crate) fn diff(
+    input: &PInputData,
+    output_a: &POutputData,
+    output_b: &POutputData,
+) -> SubmissionDiff {
+    let report_a = simulate_report(input, output_a);
+    let report_b = simulate_report(input, output_b);
+
+    let mut cars: Vec<CarDiff> = report_a
+        .cars
+        .iter()
+        .zip(report_b.cars.iter())
+        .enumerate()
+        .filter(|(_, (car_a, car_b))| car_a.points != car_b.points)
+        .map(|(car_id, (car_a, car_b))| CarDiff {
+            car_id,
+            finish_time_a: car_a.finish_time,
+            finish_time_b: car_b.finish_time,
+            delta: car_b.points as i64 - car_a.points as i64,
+        })
+        .collect();
+    cars.sort_by_key(|car| (Reverse(car.delta.abs()), car.car_id));
+
+    let schedules_a = schedules_by_intersection(output_a);
+    let schedules_b = schedules_by_intersection(output_b);
+    let stats_a = intersection_stats(input, &report_a.streets);
+    let stats_b = intersection_stats(input, &report_b.streets);
+    let mut intersections: Vec<IntersectionDiff> = stats_a
+        .into_iter()
+        .zip(stats_b)
+        .enumerate()
+        .map(|(intersection_id, (stats_a, stats_b))| {
+            let schedule_change = match (
+                schedules_a.get(&intersection_id),
+                schedules_b.get(&intersection_id),
+            ) {
+                (Some(a), Some(b)) if a.light_schedules == b.light_schedules => {
+                    ScheduleChange::Unchanged
+                }
+                (Some(_), Some(_)) => ScheduleChange::Changed,
+                (Some(_), None) => ScheduleChange::OnlyA,
+                (None, Some(_)) => ScheduleChange::OnlyB,
+                (None, None) => ScheduleChange::Unscheduled,
+            };
+            let waiting_time_delta = stats_a.waiting_time as i64 - stats_b.waiting_time as i64;
+            IntersectionDiff {
+                intersection_id,
+                schedule_change,
+                stats_a,
+                stats_b,
+                waiting_time_delta,
+            }
+        })
+        .filter(|intersection| {
+            intersection.schedule_change != ScheduleChange::Unchanged
+                && intersection.schedule_change != ScheduleChange::Unscheduled
+                || intersection.stats_a != intersection.stats_b
+        })
+        .collect();
+    intersections.sort_by_key(|intersection| {
+        (
+            Reverse(intersection.waiting_time_delta.abs()),
+            intersection.intersection_id,
+        )
+    });
+
+    SubmissionDiff {
+        score_a: report_a.score,
+        score_b: report_b.score,
+        cars,
+        intersections,
+    }
+}
+
+fn format_finish_time(finish_time: Option<Time>) -> String {
+    match finish_time {
+        Some(time) => format!("finished at {}", time),
+        None => "not finished".to_string(),
+    }
+}
+
+fn earlier(car: &CarDiff) -> &'static str {
+    match (car.finish_time_a, car.finish_time_b) {
+        (Some(a), Some(b)) if a < b => "A earlier",
+        (Some(_), Some(_)) => "B earlier",
+        (Some(_), None) => "only A",
+        (None, _) => "only B",
+    }
+}
+
+fn schedule_change_label(schedule_change: ScheduleChange) -> &'static str {
+    match schedule_change {
+        ScheduleChange::Unchanged => "unchanged",
+        ScheduleChange::Changed => "changed",
+        ScheduleChange::OnlyA => "only A",
+        ScheduleChange::OnlyB => "only B",
+        ScheduleChange::Unscheduled => "unscheduled",
+    }
+}
+
+pub(crate) fn print_diff(submission_diff: &SubmissionDiff, limit: Option<usize>) {
+    let limit = limit.unwrap_or(usize::MAX);
+    println!(
+        "score: A {} B {} ({})",
+        submission_diff.score_a.to_formatted_string(&Locale::en),
+        submission_diff.score_b.to_formatted_string(&Locale::en),
+        format_signed(submission_diff.score_b as i64 - submission_diff.score_a as i64)
+    );
+    let better_a = submission_diff
+        .cars
+        .iter()
+        .filter(|car| car.delta < 0)
+        .count();
+    println!(
+        "cars: {} better in A, {} better in B",
+        better_a,
+        submission_diff.cars.len() - better_a
+    );
+    for car in submission_diff.cars.iter().take(limit) {
+        println!(
+            "car {}: A {}, B {}, {} ({})",
+            car.car_id,
+            format_finish_time(car.finish_time_a),
+            format_finish_time(car.finish_time_b),
+            earlier(car),
+            format_signed(car.delta)
+        );
+    }
+    println!(
+        "intersections: {} differ",
+        submission_diff.intersections.len()
+    );
+    for intersection in submission_diff.intersections.iter().take(limit) {
+        println!(
+            "intersection {} [{}]: cars passed {} -> {}, waiting time {} -> {}, max queue {} -> {}, waiting time delta {}",
+            intersection.intersection_id,
+            schedule_change_label(intersection.schedule_change),
+            intersection.stats_a.cars_passed,
+            intersection.stats_b.cars_passed,
+            intersection.stats_a.waiting_time,
+            intersection.stats_b.waiting_time,
+            intersection.stats_a.max_queue_length,
+            intersection.stats_b.max_queue_length,
+            format_signed(intersection.waiting_time_delta)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::test_data;
+    use crate::diff::{diff, CarDiff, ScheduleChange};
+
+    #[test]
+    fn test_diff_example() {
+        let input_data = test_data::get_example_input_data();
+        let output_a = test_data::get_example_output_data();
+        let mut output_b = test_data::get_example_output_data();
+        // rue-d-amsterdam first: car 0 no longer waits, car 1 does
+        output_b.intersection_schedules[0].light_schedules.reverse();
+        let submission_diff = diff(&input_data, &output_a, &output_b);
+        assert_eq!(submission_diff.score_a, 1002);
+        assert_eq!(
+            submission_diff.cars,
+            vec![CarDiff {
+                car_id: 1,
+                finish_time_a: Some(4),
+                finish_time_b: Some(5),
+                delta: -1,
+            }]
+        );
+        assert_eq!(submission_diff.intersections.len(), 2);
+        let intersection = &submission_diff.intersections[0];
+        assert_eq!(intersection.intersection_id, 1);
+        assert_eq!(intersection.schedule_change, ScheduleChange::Changed);
+        assert_eq!(intersection.waiting_time_delta, -2);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::data::{PInputData, POutputData};
+use crate::score::{simulate, CarId, Observer, Score, StreetId, Time};
+use anyhow::bail;
+use num_format::{Locale, ToFormattedString};
+
+#[derive(Debug, PartialEq)]
+pub struct LightVisit {
+    pub street_id: StreetId,
+    pub arrival_time: Time,
+    pub cars_ahead: usize,
+    // None if the car is still waiting at the end of the simulation
+    pub green_time: Option<Time>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CarTimeline {
+    pub car_id: CarId,
+    pub path: Vec<StreetId>,
+    // one visit for each street of the path the car reached the end of, except the last one
+    pub visits: Vec<LightVisit>,
+    pub finish_time: Option<Time>,
+    pub points: Score,
+}
+
+struct ExplainObserver {
+    car_id: CarId,
+    visits: Vec<LightVisit>,
+    finish_time: Option<Time>,
+    points: Score,
+}
+
+impl Observer for ExplainObserver {
+    fn on_queued(&mut self, time: Time, street_id: StreetId, car_id: CarId, cars_ahead: usize) {
+        if car_id == self.car_id {
+            self.visits.push(LightVisit {
+                street_id,
+                arrival_time: time,
+                cars_ahead,
+                green_time: None,
+            });
+        }
+    }
+
+    fn on_green(&mut self, time: Time, _street_id: StreetId, car_id: CarId) {
+        if car_id == self.car_id {
+            if let Some(visit) = self.visits.last_mut() {
+                visit.green_time = Some(time);
+            }
+        }
+    }
+
+    fn on_finished(&mut self, time: Time, car_id: CarId, points: Score) {
+        if car_id == self.car_id {
+            self.finish_time = Some(time);
+            self.points = points;
+        }
+    }
+}
+
+pub fn explain_car(
+    input: &PInputData,
+    output: &POutputData,
+    car_id: CarId,
+) -> anyhow::Result<CarTimeline> {
+    if car_id >= input.body.car_paths.len() {
+        bail!(
+            "unknown car {}: there are {} cars",
+            car_id,
+            input.body.car_paths.len()
+        );
+    }
+    let street_id_of_name = input.street_id_of_name();
+    let path = input.body.car_paths[car_id]
+        .street_names
+        .iter()
+        .map(|street_name| street_id_of_name[street_name.as_str()])
+        .collect();
+    let mut observer = ExplainObserver {
+        car_id,
+        visits: vec![],
+        finish_time: None,
+        points: 0,
+    };
+    simulate(input, output, &mut observer);
+    Ok(CarTimeline {
+        car_id,
+        path,
+        visits: observer.visits,
+        finish_time: observer.finish_time,
+        points: observer.points,
+    })
+}
+
+pub fn print_timeline(input: &PInputData, timeline: &CarTimeline) {
+    let streets = &input.body.streets;
+    println!("car {}", timeline.car_id);
+    for (i, &street_id) in timeline.path.iter().enumerate() {
+        let street = &streets[street_id];
+        if i == 0 {
+            println!("starts at the end of {}", street.street_name);
+        } else {
+            println!("drives {} ({}s)", street.street_name, street.street_length);
+        }
+        match timeline.visits.get(i) {
+            Some(visit) => match visit.green_time {
+                Some(green_time) => println!(
+                    "t={}: light of {} (intersection {}), {} cars ahead, green at t={}, waited {}s",
+                    visit.arrival_time,
+                    street.street_name,
+                    street.intersection_end,
+                    visit.cars_ahead,
+                    green_time,
+                    green_time - visit.arrival_time
+                ),
+                None => {
+                    println!(
+                        "t={}: light of {} (intersection {}), {} cars ahead, still waiting when the simulation ends",
+                        visit.arrival_time, street.street_name, street.intersection_end, visit.cars_ahead
+                    );
+                    break;
+                }
+            },
+            // the last street of the path has no light
+            None if i + 1 < timeline.path.len() => {
+                println!("still driving when the simulation ends");
+                break;
+            }
+            None => (),
+        }
+    }
+    match timeline.finish_time {
+        Some(finish_time) => println!(
+            "t={}: finished, {} points",
+            finish_time,
+            timeline.points.to_formatted_string(&Locale::en)
+        ),
+        None => println!(
+            "did not finish (simulation ends at t={})",
+            input.header.simulation_duration
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::test_data;
+    use crate::explain::{explain_car, LightVisit};
+
+    #[test]
+    fn test_explain_car_example() {
+        let input_data = test_data::get_example_input_data();
+        let output_data = test_data::get_example_output_data();
+        let timeline = explain_car(&input_data, &output_data, 0).unwrap();
+        assert_eq!(timeline.path, vec![0, 1, 4, 3]);
+        assert_eq!(
+            timeline.visits,
+            vec![
+                LightVisit {
+                    street_id: 0,
+                    arrival_time: 0,
+                    cars_ahead: 0,
+                    green_time: Some(0),
+                },
+                LightVisit {
+                    street_id: 1,
+                    arrival_time: 1,
+                    cars_ahead: 0,
+                    green_time: Some(2),
+                },
+                LightVisit {
+                    street_id: 4,
+                    arrival_time: 5,
+                    cars_ahead: 0,
+                    green_time: Some(5),
+                },
+            ]
+        );
+        // rue-de-rome takes 2 seconds, the simulation ends at 6
+        assert_eq!(timeline.finish_time, None);
+        assert!(explain_car(&input_data, &output_data, 2).is_err());
+    }
+}
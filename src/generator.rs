@@ -0,0 +1,204 @@
+use crate::data::{PInputData, PIntersectionSchedule, POutputData};
+use crate::score::{StreetId, Time};
+use anyhow::bail;
+use std::str::FromStr;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Strategy {
+    /// 1 second for each street a car waits on
+    Uniform,
+    /// duration proportional to the number of cars waiting on the street
+    Proportional,
+    /// 1 second for each street, streets whose first car arrives first turn green first
+    FirstArrival,
+}
+
+impl FromStr for Strategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uniform" => Ok(Strategy::Uniform),
+            "proportional" => Ok(Strategy::Proportional),
+            "first-arrival" => Ok(Strategy::FirstArrival),
+            _ => bail!("unknown strategy: {}", s),
+        }
+    }
+}
+
+pub const STRATEGIES: &[&str] = &["uniform", "proportional", "first-arrival"];
+
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct StreetTraffic {
+    pub(crate) cars: usize,
+    // earliest arrival at the light assuming no car ever waits
+    pub(crate) first_arrival: Option<Time>,
+}
+
+// traffic at the end of each street, the last street of a path has no light to wait for
+pub(crate) fn street_traffic(input: &PInputData) -> Vec<StreetTraffic> {
+    let street_id_of_name = input.street_id_of_name();
+    let mut traffic = vec![StreetTraffic::default(); input.body.streets.len()];
+    for car_path in &input.body.car_paths {
+        let mut time: Time = 0;
+        let waiting_streets = car_path.street_names.len().saturating_sub(1);
+        for (i, street_name) in car_path.street_names[..waiting_streets].iter().enumerate() {
+            let street_id = street_id_of_name[street_name.as_str()];
+            if i > 0 {
+                time += input.body.streets[street_id].street_length;
+            }
+            let street_traffic = &mut traffic[street_id];
+            street_traffic.cars += 1;
+            street_traffic.first_arrival = Some(
+                street_traffic
+                    .first_arrival
+                    .map_or(time, |first_arrival| first_arrival.min(time)),
+            );
+        }
+    }
+    traffic
+}
+
+/// Builds a schedule for every intersection with at least one street cars wait on.
+pub fn generate(input: &PInputData, strategy: Strategy) -> POutputData {
+    let traffic = street_traffic(input);
+    let mut incoming_streets: Vec<Vec<StreetId>> = vec![vec![]; input.header.intersections];
+    for (street_id, street) in input.body.streets.iter().enumerate() {
+        if traffic[street_id].cars > 0 {
+            incoming_streets[street.intersection_end].push(street_id);
+        }
+    }
+    let max_duration = input.header.simulation_duration.max(1);
+    let intersection_schedules: Vec<PIntersectionSchedule> = incoming_streets
+        .into_iter()
+        .enumerate()
+        .filter(|(_, street_ids)| !street_ids.is_empty())
+        .map(|(intersection_id, mut street_ids)| {
+            if strategy == Strategy::FirstArrival {
+                street_ids.sort_by_key(|&street_id| (traffic[street_id].first_arrival, street_id));
+            }
+            let min_cars = street_ids
+                .iter()
+                .map(|&street_id| traffic[street_id].cars)
+                .min()
+                .unwrap_or(1);
+            let light_schedules: Vec<(String, usize)> = street_ids
+                .iter()
+                .map(|&street_id| {
+                    let duration = match strategy {
+                        Strategy::Uniform | Strategy::FirstArrival => 1,
+                        Strategy::Proportional => {
+                            // rounded so that the least used street gets 1 second
+                            ((traffic[street_id].cars + min_cars / 2) / min_cars)
+                                .clamp(1, max_duration)
+                        }
+                    };
+                    (input.body.streets[street_id].street_name.clone(), duration)
+                })
+                .collect();
+            PIntersectionSchedule {
+                intersection_id,
+                incoming_streets: light_schedules.len(),
+                light_schedules,
+            }
+        })
+        .collect();
+    POutputData {
+        schedules: intersection_schedules.len(),
+        intersection_schedules,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{test_data, PCarPath};
+    use crate::generator::{generate, Strategy};
+    use crate::score::compute_score;
+
+    fn light_schedules(strategy: Strategy) -> Vec<(usize, Vec<(String, usize)>)> {
+        let input_data = test_data::get_example_input_data();
+        generate(&input_data, strategy)
+            .intersection_schedules
+            .into_iter()
+            .map(|schedule| (schedule.intersection_id, schedule.light_schedules))
+            .collect()
+    }
+
+    #[test]
+    fn test_generate_uniform() {
+        assert_eq!(
+            light_schedules(Strategy::Uniform),
+            vec![
+                (0, vec![("rue-de-londres".to_string(), 1)]),
+                (
+                    1,
+                    vec![
+                        ("rue-d-amsterdam".to_string(), 1),
+                        ("rue-d-athenes".to_string(), 1)
+                    ]
+                ),
+                (2, vec![("rue-de-moscou".to_string(), 1)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generate_proportional() {
+        // rue-de-moscou is used by both cars but is alone at its intersection
+        assert_eq!(
+            light_schedules(Strategy::Proportional),
+            light_schedules(Strategy::Uniform)
+        );
+
+        let mut input_data = test_data::get_example_input_data();
+        // 1 more car waits on rue-d-amsterdam and 4 more on rue-d-athenes
+        let car_path = |street_names: &[&str]| PCarPath {
+            streets: street_names.len(),
+            street_names: street_names.iter().map(|name| name.to_string()).collect(),
+        };
+        input_data.body.car_paths.push(car_path(&[
+            "rue-de-londres",
+            "rue-d-amsterdam",
+            "rue-de-moscou",
+        ]));
+        for _ in 0..4 {
+            input_data
+                .body
+                .car_paths
+                .push(car_path(&["rue-d-athenes", "rue-de-moscou"]));
+        }
+        input_data.header.cars = input_data.body.car_paths.len();
+        let output_data = generate(&input_data, Strategy::Proportional);
+        // 2 cars on rue-d-amsterdam get 1 second, 5 on rue-d-athenes get 5 / 2 rounded
+        assert_eq!(
+            output_data.intersection_schedules[1].light_schedules,
+            vec![
+                ("rue-d-amsterdam".to_string(), 1),
+                ("rue-d-athenes".to_string(), 3)
+            ]
+        );
+        // 2 cars on rue-de-londres, alone at intersection 0
+        assert_eq!(
+            output_data.intersection_schedules[0].light_schedules,
+            vec![("rue-de-londres".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_generate_first_arrival() {
+        // car 1 is at the end of rue-d-athenes at time 0, car 0 reaches rue-d-amsterdam at time 1
+        assert_eq!(
+            light_schedules(Strategy::FirstArrival)[1],
+            (
+                1,
+                vec![
+                    ("rue-d-athenes".to_string(), 1),
+                    ("rue-d-amsterdam".to_string(), 1)
+                ]
+            )
+        );
+        let input_data = test_data::get_example_input_data();
+        let output_data = generate(&input_data, Strategy::FirstArrival);
+        assert_eq!(compute_score(&input_data, &output_data), 2002);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::analysis::street_usage;
+use crate::data::PInputData;
+use crate::report::SimulationReport;
+use anyhow::bail;
+use std::fmt::Write as FmtWrite;
+use std::str::FromStr;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum GraphFormat {
+    Dot,
+    GraphMl,
+}
+
+impl FromStr for GraphFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(GraphFormat::Dot),
+            "graphml" => Ok(GraphFormat::GraphMl),
+            _ => bail!("unknown graph format: {}", s),
+        }
+    }
+}
+
+pub const GRAPH_FORMATS: &[&str] = &["dot", "graphml"];
+
+// gray for 0 up to red for max
+fn heat_color(weight: usize, max_weight: usize) -> String {
+    let ratio = if max_weight == 0 {
+        0.0
+    } else {
+        weight as f64 / max_weight as f64
+    };
+    let red = (160.0 + 95.0 * ratio) as u8;
+    let other = (160.0 * (1.0 - ratio)) as u8;
+    format!("#{:02x}{:02x}{:02x}", red, other, other)
+}
+
+pub(crate) fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Exports the city as a directed graph: intersections are nodes, streets are edges.
+///
+/// Edges are weighted by the number of cars using them, or by the simulated waiting time
+/// at their light when a simulation report is provided.
+pub fn export_graph(
+    input: &PInputData,
+    report: Option<&SimulationReport>,
+    format: GraphFormat,
+) -> String {
+    let usage = street_usage(input);
+    let weights: Vec<usize> = match report {
+        Some(report) => report.streets.iter().map(|s| s.waiting_time).collect(),
+        None => usage.clone(),
+    };
+    let max_weight = weights.iter().copied().max().unwrap_or(0);
+    let mut s = String::new();
+    match format {
+        GraphFormat::Dot => {
+            writeln!(s, "digraph city {{").unwrap();
+            for intersection_id in 0..input.header.intersections {
+                writeln!(s, "  {};", intersection_id).unwrap();
+            }
+            for (street_id, street) in input.body.streets.iter().enumerate() {
+                let mut label = format!(
+                    "{} ({}s)\\ncars: {}",
+                    street.street_name, street.street_length, usage[street_id]
+                );
+                if let Some(report) = report {
+                    let stats = &report.streets[street_id];
+                    write!(
+                        label,
+                        "\\nwaiting: {}s\\nmax queue: {}",
+                        stats.waiting_time, stats.max_queue_length
+                    )
+                    .unwrap();
+                }
+                writeln!(
+                    s,
+                    "  {} -> {} [label=\"{}\", weight={}, penwidth={:.1}, color=\"{}\"];",
+                    street.intersection_start,
+                    street.intersection_end,
+                    label,
+                    weights[street_id],
+                    1.0 + 4.0 * weights[street_id] as f64 / max_weight.max(1) as f64,
+                    heat_color(weights[street_id], max_weight)
+                )
+                .unwrap();
+            }
+            writeln!(s, "}}").unwrap();
+        }
+        GraphFormat::GraphMl => {
+            writeln!(s, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>").unwrap();
+            writeln!(
+                s,
+                "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">"
+            )
+            .unwrap();
+            let mut keys = vec![
+                ("name", "string"),
+                ("length", "int"),
+                ("cars", "int"),
+                ("weight", "int"),
+                ("color", "string"),
+            ];
+            if report.is_some() {
+                keys.extend(vec![
+                    ("cars_passed", "int"),
+                    ("waiting_time", "int"),
+                    ("max_queue_length", "int"),
+                ]);
+            }
+            for (key, key_type) in &keys {
+                writeln!(
+                    s,
+                    "  <key id=\"{0}\" for=\"edge\" attr.name=\"{0}\" attr.type=\"{1}\"/>",
+                    key, key_type
+                )
+                .unwrap();
+            }
+            writeln!(s, "  <graph id=\"city\" edgedefault=\"directed\">").unwrap();
+            for intersection_id in 0..input.header.intersections {
+                writeln!(s, "    <node id=\"n{}\"/>", intersection_id).unwrap();
+            }
+            for (street_id, street) in input.body.streets.iter().enumerate() {
+                let mut values = vec![
+                    escape_xml(&street.street_name),
+                    street.street_length.to_string(),
+                    usage[street_id].to_string(),
+                    weights[street_id].to_string(),
+                    heat_color(weights[street_id], max_weight),
+                ];
+                if let Some(report) = report {
+                    let stats = &report.streets[street_id];
+                    values.extend(vec![
+                        stats.cars_passed.to_string(),
+                        stats.waiting_time.to_string(),
+                        stats.max_queue_length.to_string(),
+                    ]);
+                }
+                writeln!(
+                    s,
+                    "    <edge id=\"e{}\" source=\"n{}\" target=\"n{}\">",
+                    street_id, street.intersection_start, street.intersection_end
+                )
+                .unwrap();
+                for ((key, _), value) in keys.iter().zip(values) {
+                    writeln!(s, "      <data key=\"{}\">{}</data>", key, value).unwrap();
+                }
+                writeln!(s, "    </edge>").unwrap();
+            }
+            writeln!(s, "  </graph>").unwrap();
+            writeln!(s, "</graphml>").unwrap();
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::test_data;
+    use crate::graph::{export_graph, GraphFormat};
+    use crate::report::simulate_report;
+
+    #[test]
+    fn test_export_dot_example() {
+        let input_data = test_data::get_example_input_data();
+        let dot = export_graph(&input_data, None, GraphFormat::Dot);
+        assert!(dot.starts_with("digraph city {\n"));
+        // rue-de-moscou is used by both cars
+        assert!(dot.contains(
+            "  1 -> 2 [label=\"rue-de-moscou (3s)\\ncars: 2\", weight=2, penwidth=5.0, color=\"#ff0000\"];\n"
+        ));
+    }
+
+    #[test]
+    fn test_export_graphml_example() {
+        let input_data = test_data::get_example_input_data();
+        let output_data = test_data::get_example_output_data();
+        let report = simulate_report(&input_data, &output_data);
+        let graphml = export_graph(&input_data, Some(&report), GraphFormat::GraphMl);
+        assert_eq!(graphml.matches("<node ").count(), 4);
+        assert_eq!(graphml.matches("<edge ").count(), 5);
+        assert!(graphml.contains("<data key=\"waiting_time\">1</data>"));
+    }
+}
@@ -0,0 +1,291 @@
+use crate::data::{PInputData, POutputData};
+use crate::graph::escape_xml;
+use crate::report::{simulate_report, simulate_timeline, SimulationReport, Timeline};
+use crate::score::Time;
+use num_format::{Locale, ToFormattedString};
+use std::fmt::Write as FmtWrite;
+
+const WIDTH: f64 = 800.0;
+const HEIGHT: f64 = 300.0;
+const MARGIN: f64 = 50.0;
+const HISTOGRAM_BINS: usize = 50;
+const TIMELINE_POINTS: usize = 200;
+const CONGESTED_STREETS: usize = 20;
+
+fn axes(s: &mut String, x_label: &str, x_max: usize, y_label: &str, y_max: usize) {
+    let (left, bottom) = (MARGIN, HEIGHT - MARGIN);
+    writeln!(
+        s,
+        "<line x1=\"{0}\" y1=\"{1}\" x2=\"{2}\" y2=\"{1}\" stroke=\"black\"/>",
+        left,
+        bottom,
+        WIDTH - MARGIN
+    )
+    .unwrap();
+    writeln!(
+        s,
+        "<line x1=\"{0}\" y1=\"{1}\" x2=\"{0}\" y2=\"{2}\" stroke=\"black\"/>",
+        left, bottom, MARGIN
+    )
+    .unwrap();
+    writeln!(
+        s,
+        "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+        WIDTH / 2.0,
+        HEIGHT - 10.0,
+        x_label
+    )
+    .unwrap();
+    writeln!(
+        s,
+        "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>",
+        WIDTH - MARGIN,
+        bottom + 15.0,
+        x_max
+    )
+    .unwrap();
+    writeln!(
+        s,
+        "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">0</text>",
+        left - 5.0,
+        bottom
+    )
+    .unwrap();
+    writeln!(
+        s,
+        "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>",
+        left - 5.0,
+        MARGIN + 5.0,
+        y_max
+    )
+    .unwrap();
+    writeln!(
+        s,
+        "<text x=\"{}\" y=\"{}\">{}</text>",
+        left,
+        MARGIN - 10.0,
+        y_label
+    )
+    .unwrap();
+}
+
+fn finish_time_histogram(report: &SimulationReport, simulation_duration: Time) -> String {
+    let bin_size = (simulation_duration / HISTOGRAM_BINS).max(1);
+    let bins = simulation_duration / bin_size + 1;
+    let mut counts = vec![0usize; bins];
+    for finish_time in report.cars.iter().filter_map(|car| car.finish_time) {
+        counts[finish_time / bin_size] += 1;
+    }
+    let max_count = counts.iter().copied().max().unwrap_or(0).max(1);
+    let bar_width = (WIDTH - 2.0 * MARGIN) / bins as f64;
+    let mut s = String::new();
+    writeln!(
+        s,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">",
+        WIDTH, HEIGHT
+    )
+    .unwrap();
+    for (bin, &count) in counts.iter().enumerate() {
+        let height = (HEIGHT - 2.0 * MARGIN) * count as f64 / max_count as f64;
+        writeln!(
+            s,
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"steelblue\"><title>t={}..{}: {} cars</title></rect>",
+            MARGIN + bin as f64 * bar_width,
+            HEIGHT - MARGIN - height,
+            bar_width,
+            height,
+            bin * bin_size,
+            (bin + 1) * bin_size - 1,
+            count
+        )
+        .unwrap();
+    }
+    axes(
+        &mut s,
+        "finish time (s)",
+        simulation_duration,
+        "cars",
+        max_count,
+    );
+    writeln!(s, "</svg>").unwrap();
+    s
+}
+
+fn polyline(values: &[usize], max_value: usize, color: &str) -> String {
+    let step = (WIDTH - 2.0 * MARGIN) / values.len().max(1) as f64;
+    let points: Vec<String> = values
+        .iter()
+        .enumerate()
+        .map(|(i, &value)| {
+            format!(
+                "{:.1},{:.1}",
+                MARGIN + (i + 1) as f64 * step,
+                HEIGHT - MARGIN - (HEIGHT - 2.0 * MARGIN) * value as f64 / max_value as f64
+            )
+        })
+        .collect();
+    format!(
+        "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"/>",
+        points.join(" "),
+        color
+    )
+}
+
+fn cars_over_time(timeline: &Timeline, cars: usize, simulation_duration: Time) -> String {
+    let in_flight: Vec<usize> = timeline
+        .waiting
+        .iter()
+        .zip(&timeline.finished)
+        .map(|(waiting, finished)| cars - waiting - finished)
+        .collect();
+    let max_value = in_flight
+        .iter()
+        .chain(&timeline.waiting)
+        .copied()
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let mut s = String::new();
+    writeln!(
+        s,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">",
+        WIDTH, HEIGHT
+    )
+    .unwrap();
+    writeln!(s, "{}", polyline(&in_flight, max_value, "seagreen")).unwrap();
+    writeln!(s, "{}", polyline(&timeline.waiting, max_value, "firebrick")).unwrap();
+    writeln!(
+        s,
+        "<text x=\"{0}\" y=\"{1}\" text-anchor=\"end\" fill=\"seagreen\">in flight</text><text x=\"{0}\" y=\"{2}\" text-anchor=\"end\" fill=\"firebrick\">waiting</text>",
+        WIDTH - MARGIN,
+        MARGIN - 25.0,
+        MARGIN - 10.0
+    )
+    .unwrap();
+    axes(&mut s, "time (s)", simulation_duration, "cars", max_value);
+    writeln!(s, "</svg>").unwrap();
+    s
+}
+
+fn congested_streets(input: &PInputData, report: &SimulationReport) -> String {
+    let mut street_ids: Vec<usize> = (0..report.streets.len())
+        .filter(|&street_id| report.streets[street_id].waiting_time > 0)
+        .collect();
+    street_ids.sort_by_key(|&street_id| std::cmp::Reverse(report.streets[street_id].waiting_time));
+    street_ids.truncate(CONGESTED_STREETS);
+    let max_waiting_time = street_ids
+        .first()
+        .map(|&street_id| report.streets[street_id].waiting_time)
+        .unwrap_or(1);
+    let label_width = 200.0;
+    let bar_height = 20.0;
+    let mut s = String::new();
+    writeln!(
+        s,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">",
+        WIDTH,
+        bar_height * street_ids.len().max(1) as f64 + 10.0
+    )
+    .unwrap();
+    for (row, &street_id) in street_ids.iter().enumerate() {
+        let street = &input.body.streets[street_id];
+        let stats = &report.streets[street_id];
+        let y = row as f64 * bar_height;
+        let width = (WIDTH - label_width - 2.0 * MARGIN) * stats.waiting_time as f64
+            / max_waiting_time as f64;
+        writeln!(
+            s,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>",
+            label_width - 5.0,
+            y + 15.0,
+            escape_xml(&street.street_name)
+        )
+        .unwrap();
+        writeln!(
+            s,
+            "<rect x=\"{}\" y=\"{}\" width=\"{:.1}\" height=\"{}\" fill=\"firebrick\"><title>intersection {}, max queue {}</title></rect>",
+            label_width,
+            y + 3.0,
+            width,
+            bar_height - 6.0,
+            street.intersection_end,
+            stats.max_queue_length
+        )
+        .unwrap();
+        writeln!(
+            s,
+            "<text x=\"{:.1}\" y=\"{}\">{}s</text>",
+            label_width + width + 5.0,
+            y + 15.0,
+            stats.waiting_time.to_formatted_string(&Locale::en)
+        )
+        .unwrap();
+    }
+    writeln!(s, "</svg>").unwrap();
+    s
+}
+
+/// Standalone HTML page with inline SVG charts of a simulated submission: finish time
+/// histogram, cars in flight and waiting over time, and the most congested streets.
+pub fn heatmap_report(input: &PInputData, output: &POutputData, title: &str) -> String {
+    let simulation_duration = input.header.simulation_duration;
+    let report = simulate_report(input, output);
+    let bucket_size = simulation_duration.div_ceil(TIMELINE_POINTS).max(1);
+    let timeline = simulate_timeline(input, output, bucket_size);
+    let cars = input.body.car_paths.len();
+    let finished = report
+        .cars
+        .iter()
+        .filter(|car| car.finish_time.is_some())
+        .count();
+
+    let mut s = String::new();
+    writeln!(s, "<!DOCTYPE html>").unwrap();
+    writeln!(
+        s,
+        "<html><head><meta charset=\"utf-8\"><title>{0}</title></head><body style=\"font-family: sans-serif\">\n<h1>{0}</h1>",
+        escape_xml(title)
+    )
+    .unwrap();
+    writeln!(
+        s,
+        "<p>score: {}, finished cars: {} / {}, simulation duration: {}s</p>",
+        report.score.to_formatted_string(&Locale::en),
+        finished.to_formatted_string(&Locale::en),
+        cars.to_formatted_string(&Locale::en),
+        simulation_duration.to_formatted_string(&Locale::en)
+    )
+    .unwrap();
+    writeln!(s, "<h2>Finish times</h2>").unwrap();
+    s.push_str(&finish_time_histogram(&report, simulation_duration));
+    writeln!(s, "<h2>Cars over time</h2>").unwrap();
+    s.push_str(&cars_over_time(&timeline, cars, simulation_duration));
+    writeln!(
+        s,
+        "<h2>Top {} congested streets (total waiting time)</h2>",
+        CONGESTED_STREETS
+    )
+    .unwrap();
+    s.push_str(&congested_streets(input, &report));
+    writeln!(s, "</body></html>").unwrap();
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::test_data;
+    use crate::heatmap::heatmap_report;
+
+    #[test]
+    fn test_heatmap_report_example() {
+        let input_data = test_data::get_example_input_data();
+        let output_data = test_data::get_example_output_data();
+        let html = heatmap_report(&input_data, &output_data, "example");
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert_eq!(html.matches("<svg ").count(), 3);
+        assert!(html.contains("score: 1,002, finished cars: 1 / 2"));
+        // only rue-d-amsterdam has waiting cars
+        assert!(html.contains(">rue-d-amsterdam</text>"));
+        assert!(!html.contains(">rue-de-moscou</text>"));
+    }
+}
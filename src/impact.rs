@@ -0,0 +1,138 @@
+use crate::data::{PInputData, POutputData};
+use crate::parallel::parallel_map_until;
+use crate::report::{format_signed, simulate_report};
+use crate::score::{CarId, Score, Simulator, Time};
+use num_format::{Locale, ToFormattedString};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, PartialEq)]
+pub struct CarImpact {
+    pub car_id: CarId,
+    pub waiting_time: Time,
+    pub points: Score,
+    // score without the car minus score with it
+    pub score_delta: i64,
+    // points other cars gain when the car is removed
+    pub others_delta: i64,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ImpactReport {
+    pub score: Score,
+    // most congesting cars first
+    pub impacts: Vec<CarImpact>,
+    // candidates not evaluated before the time budget ran out
+    pub skipped: usize,
+}
+
+/// Re-scores `output` without each of the `top` cars that wait the most (all cars if `None`).
+///
+/// Cars are evaluated in decreasing waiting time order on `threads` threads until
+/// `time_budget` is spent.
+pub fn car_impacts(
+    input: &PInputData,
+    output: &POutputData,
+    top: Option<usize>,
+    time_budget: Duration,
+    threads: usize,
+) -> ImpactReport {
+    let deadline = Instant::now() + time_budget;
+    let report = simulate_report(input, output);
+    let mut candidates: Vec<CarId> = (0..report.cars.len()).collect();
+    candidates.sort_by_key(|&car_id| (std::cmp::Reverse(report.cars[car_id].waiting_time), car_id));
+    candidates.truncate(top.unwrap_or(usize::MAX));
+
+    let simulator = Simulator::new(input);
+    let scores = parallel_map_until(&candidates, threads, Some(deadline), |&car_id| {
+        simulator.score_without_car(output, car_id)
+    });
+    let skipped = scores.iter().filter(|score| score.is_none()).count();
+    let mut impacts: Vec<CarImpact> = candidates
+        .iter()
+        .zip(scores)
+        .filter_map(|(&car_id, score_without)| {
+            let score_without = score_without? as i64;
+            let car_stats = &report.cars[car_id];
+            let score_delta = score_without - report.score as i64;
+            Some(CarImpact {
+                car_id,
+                waiting_time: car_stats.waiting_time,
+                points: car_stats.points,
+                score_delta,
+                others_delta: score_delta + car_stats.points as i64,
+            })
+        })
+        .collect();
+    impacts.sort_by_key(|impact| (std::cmp::Reverse(impact.others_delta), impact.car_id));
+    ImpactReport {
+        score: report.score,
+        impacts,
+        skipped,
+    }
+}
+
+pub fn print_impacts(report: &ImpactReport, limit: Option<usize>) {
+    println!("score: {}", report.score.to_formatted_string(&Locale::en));
+    if report.skipped > 0 {
+        println!(
+            "time budget spent: {} cars not evaluated",
+            report.skipped.to_formatted_string(&Locale::en)
+        );
+    }
+    for impact in report.impacts.iter().take(limit.unwrap_or(usize::MAX)) {
+        println!(
+            "car {}: waited {}s, {} points, without it: others {}, score {}",
+            impact.car_id,
+            impact.waiting_time.to_formatted_string(&Locale::en),
+            impact.points.to_formatted_string(&Locale::en),
+            format_signed(impact.others_delta),
+            format_signed(impact.score_delta)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{test_data, PCarPath};
+    use crate::impact::{car_impacts, CarImpact};
+    use std::time::Duration;
+
+    #[test]
+    fn test_car_impacts_example() {
+        let mut input_data = test_data::get_example_input_data();
+        // same path as car 1, waits 1 second behind it at rue-d-athenes
+        input_data.body.car_paths.push(PCarPath {
+            streets: 3,
+            street_names: vec![
+                "rue-d-athenes".to_string(),
+                "rue-de-moscou".to_string(),
+                "rue-de-londres".to_string(),
+            ],
+        });
+        input_data.header.cars = 3;
+        let output_data = test_data::get_example_output_data();
+        let report = car_impacts(&input_data, &output_data, None, Duration::from_secs(60), 2);
+        assert_eq!(report.score, 2003);
+        assert_eq!(report.skipped, 0);
+        assert_eq!(
+            report.impacts[0],
+            CarImpact {
+                car_id: 1,
+                waiting_time: 0,
+                points: 1002,
+                score_delta: -1001,
+                others_delta: 1,
+            }
+        );
+        let report = car_impacts(
+            &input_data,
+            &output_data,
+            Some(1),
+            Duration::from_secs(60),
+            2,
+        );
+        // car 0 and car 2 both waited 1 second, car 0 comes first
+        assert_eq!(report.impacts.len(), 1);
+        assert_eq!(report.impacts[0].car_id, 0);
+    }
+}
@@ -0,0 +1,126 @@
+use crate::data::POutputData;
+use crate::score::{StreetId, StreetLength, Time};
+use ahash::AHashMap;
+
+/// Green from `offset` (included) to `offset + duration` (excluded) in each cycle of
+/// `period` seconds, never green when `duration` is 0.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) struct LightSchedule {
+    pub(crate) offset: Time,
+    pub(crate) duration: Time,
+    pub(crate) period: Time,
+}
+
+impl LightSchedule {
+    pub(crate) fn never_green(&self) -> bool {
+        self.duration == 0
+    }
+
+    /// First time at or after `time` the light is green.
+    pub(crate) fn next_green(&self, time: Time) -> Option<Time> {
+        if self.never_green() {
+            // period may be 0 as well
+            return None;
+        }
+        let tmod = time % self.period;
+        if tmod < self.offset {
+            Some(time + self.offset - tmod)
+        } else if tmod < self.offset + self.duration {
+            Some(time)
+        } else {
+            Some(time + self.period - tmod + self.offset)
+        }
+    }
+}
+
+/// Light schedule of each street, indexed by street id.
+///
+/// Streets missing from the output are never green.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LightSchedules(Vec<LightSchedule>);
+
+impl LightSchedules {
+    pub(crate) fn new(
+        output: &POutputData,
+        street_name_id_length: &AHashMap<String, (StreetId, StreetLength)>,
+    ) -> Self {
+        let mut light_schedules = vec![LightSchedule::default(); street_name_id_length.len()];
+        for intersection_schedule in &output.intersection_schedules {
+            let period: Time = intersection_schedule
+                .light_schedules
+                .iter()
+                .map(|(_, duration)| duration)
+                .sum();
+            let mut offset: Time = 0;
+            for (street_name, duration) in &intersection_schedule.light_schedules {
+                let (street_id, _) = street_name_id_length
+                    .get(street_name)
+                    .unwrap_or_else(|| panic!("unknown street name: {}", street_name));
+                light_schedules[*street_id] = LightSchedule {
+                    offset,
+                    duration: *duration,
+                    period,
+                };
+                offset += duration;
+            }
+        }
+        LightSchedules(light_schedules)
+    }
+
+    pub(crate) fn never_green(&self, street_id: StreetId) -> bool {
+        self.0[street_id].never_green()
+    }
+
+    /// First time at or after `time` `street_id` is green.
+    pub(crate) fn next_green(&self, street_id: StreetId, time: Time) -> Option<Time> {
+        self.0[street_id].next_green(time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::test_data;
+    use crate::lights::{LightSchedule, LightSchedules};
+    use ahash::AHashMap;
+
+    #[test]
+    fn test_next_green() {
+        let light_schedule = LightSchedule {
+            offset: 2,
+            duration: 3,
+            period: 7,
+        };
+        let is_green = |time| (2..5).contains(&(time % 7));
+        for time in 0..30 {
+            let next_green = (time..).find(|&t| is_green(t));
+            assert_eq!(light_schedule.next_green(time), next_green, "at {}", time);
+        }
+        assert_eq!(LightSchedule::default().next_green(3), None);
+    }
+
+    #[test]
+    fn test_light_schedules_example() {
+        let input_data = test_data::get_example_input_data();
+        let output_data = test_data::get_example_output_data();
+        let street_name_id_length = input_data
+            .body
+            .streets
+            .iter()
+            .enumerate()
+            .map(|(street_id, street)| {
+                (
+                    street.street_name.clone(),
+                    (street_id, street.street_length),
+                )
+            })
+            .collect::<AHashMap<_, _>>();
+        let light_schedules = LightSchedules::new(&output_data, &street_name_id_length);
+        // intersection 1: rue-d-athenes 2s then rue-d-amsterdam 1s
+        assert_eq!(light_schedules.next_green(2, 2), Some(3));
+        assert_eq!(light_schedules.next_green(1, 0), Some(2));
+        assert_eq!(light_schedules.next_green(1, 3), Some(5));
+        // rue-de-rome is not scheduled
+        assert!(light_schedules.never_green(3));
+        assert_eq!(light_schedules.next_green(3, 0), None);
+    }
+}
@@ -2,19 +2,54 @@
 extern crate clap;
 extern crate anyhow;
 
-use crate::parser::{parse_input, parse_output};
-use crate::score::{compute_score, Score};
+use crate::cycle_order::reorder_cycles;
+use crate::generator::{generate, Strategy};
+use crate::graph::{export_graph, GraphFormat};
+use crate::normalize::normalize;
+use crate::optimizer::{optimize, Method, OptimizerConfig};
+use crate::parser::{parse_input_file, parse_output_file};
+use crate::progress::{cancel_on_ctrl_c, ProgressLine};
+use crate::report::{format_signed, simulate_report};
+use crate::score::{compute_score, Deadline, Score, ScoringRules, Simulator, StartPosition, Time};
+use crate::starvation::{score_with_starvation, warn_starvation};
+use crate::writer::write_output_file;
 use anyhow::bail;
-use log::info;
+use clap::ArgMatches;
 use num_format::{Locale, ToFormattedString};
-use std::fs::read_to_string;
-use std::path::PathBuf;
-use std::str::FromStr;
+use std::fs::{copy, write};
+use std::sync::Arc;
+use std::time::Duration;
 
+mod analysis;
+mod checkpoint;
 mod cli;
+mod cycle_order;
 mod data;
+mod diff;
+mod explain;
+mod generator;
+mod graph;
+mod heatmap;
+mod impact;
+mod lights;
+mod merge;
+mod normalize;
+mod optimizer;
+mod parallel;
 mod parser;
+mod progress;
+mod query;
+mod rank;
+mod report;
 mod score;
+mod sensitivity;
+mod starvation;
+mod stats;
+mod sweep;
+mod timeline;
+mod validate;
+mod watch;
+mod writer;
 
 fn main() -> anyhow::Result<()> {
     // cf https://crates.io/crates/env_logger
@@ -22,6 +57,405 @@ fn main() -> anyhow::Result<()> {
 
     // parse command line arguments
     let matches = cli::get_app().get_matches();
+    match matches.subcommand() {
+        ("diff", Some(diff_matches)) => diff_command(diff_matches),
+        ("generate", Some(generate_matches)) => generate_command(generate_matches),
+        ("optimize", Some(optimize_matches)) => optimize_command(optimize_matches),
+        ("reorder", Some(reorder_matches)) => reorder_command(reorder_matches),
+        ("normalize", Some(normalize_matches)) => normalize_command(normalize_matches),
+        ("stats", Some(stats_matches)) => stats_command(stats_matches),
+        ("explain", Some(explain_matches)) => explain_command(explain_matches),
+        ("graph", Some(graph_matches)) => graph_command(graph_matches),
+        ("heatmap", Some(heatmap_matches)) => heatmap_command(heatmap_matches),
+        ("sweep", Some(sweep_matches)) => sweep_command(sweep_matches),
+        ("sensitivity", Some(sensitivity_matches)) => sensitivity_command(sensitivity_matches),
+        ("impact", Some(impact_matches)) => impact_command(impact_matches),
+        ("rank", Some(rank_matches)) => rank_command(rank_matches),
+        ("merge", Some(merge_matches)) => merge_command(merge_matches),
+        ("checkpoint", Some(checkpoint_matches)) => checkpoint_command(checkpoint_matches),
+        ("resume", Some(resume_matches)) => resume_command(resume_matches),
+        ("query", Some(query_matches)) => query_command(query_matches),
+        ("timeline", Some(timeline_matches)) => timeline_command(timeline_matches),
+        _ => score_command(&matches),
+    }
+}
+
+/// Value of an optional `usize` argument, `None` when absent.
+fn optional_usize(matches: &ArgMatches, name: &str) -> anyhow::Result<Option<usize>> {
+    if matches.is_present(name) {
+        Ok(Some(value_t!(matches, name, usize)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// `--threads`, cf `parallel::default_threads` when absent.
+fn threads(matches: &ArgMatches) -> anyhow::Result<usize> {
+    Ok(optional_usize(matches, "threads")?.unwrap_or_else(parallel::default_threads))
+}
+
+/// Number of seconds argument, rejected when negative, infinite or not a number.
+fn duration_secs(matches: &ArgMatches, name: &str) -> anyhow::Result<Duration> {
+    let secs = value_t!(matches, name, f64)?;
+    match Duration::try_from_secs_f64(secs) {
+        Ok(duration) => Ok(duration),
+        Err(_) => bail!("--{}: invalid number of seconds: {}", name, secs),
+    }
+}
+
+fn diff_command(diff_matches: &ArgMatches) -> anyhow::Result<()> {
+    let input_data = parse_input_file(diff_matches.value_of("input").expect("compulsory"))?;
+    let output_a = parse_output_file(diff_matches.value_of("output_a").expect("compulsory"))?;
+    let output_b = parse_output_file(diff_matches.value_of("output_b").expect("compulsory"))?;
+    let limit = optional_usize(diff_matches, "limit")?;
+    diff::print_diff(&diff::diff(&input_data, &output_a, &output_b), limit);
+    Ok(())
+}
+
+fn generate_command(generate_matches: &ArgMatches) -> anyhow::Result<()> {
+    let input_data = parse_input_file(generate_matches.value_of("input").expect("compulsory"))?;
+    let output_file_path = generate_matches.value_of("output").expect("compulsory");
+    let strategy = value_t!(generate_matches, "strategy", Strategy)?;
+    let output_data = generate(&input_data, strategy);
+    write_output_file(output_file_path, &output_data)?;
+    let score = compute_score(&input_data, &output_data);
+    println!(
+        "{} score: {}",
+        output_file_path,
+        score.to_formatted_string(&Locale::en)
+    );
+    Ok(())
+}
+
+fn optimize_command(optimize_matches: &ArgMatches) -> anyhow::Result<()> {
+    let input_data = parse_input_file(optimize_matches.value_of("input").expect("compulsory"))?;
+    let output_file_path = optimize_matches.value_of("output").expect("compulsory");
+    let initial = match optimize_matches.value_of("from") {
+        Some(from) => parse_output_file(from)?,
+        None => generate(
+            &input_data,
+            value_t!(optimize_matches, "strategy", Strategy)?,
+        ),
+    };
+    let config = OptimizerConfig {
+        method: value_t!(optimize_matches, "method", Method)?,
+        time_budget: duration_secs(optimize_matches, "time")?,
+        max_iterations: None,
+        seed: value_t!(optimize_matches, "seed", u64)?,
+        temperature: value_t!(optimize_matches, "temperature", f64)?,
+        skip_unreachable: optimize_matches.is_present("skip_unreachable"),
+    };
+    optimize(
+        &input_data,
+        &initial,
+        &config,
+        output_file_path,
+        &cancel_on_ctrl_c()?,
+    )?;
+    Ok(())
+}
+
+fn reorder_command(reorder_matches: &ArgMatches) -> anyhow::Result<()> {
+    let input_data = parse_input_file(reorder_matches.value_of("input").expect("compulsory"))?;
+    let output_data = parse_output_file(reorder_matches.value_of("output").expect("compulsory"))?;
+    let reordered_file_path = reorder_matches.value_of("reordered").expect("compulsory");
+    let passes = value_t!(reorder_matches, "passes", usize)?;
+    let score_before = compute_score(&input_data, &output_data);
+    let (reordered, score_after) = reorder_cycles(&input_data, &output_data, passes);
+    write_output_file(reordered_file_path, &reordered)?;
+    println!(
+        "score before: {}",
+        score_before.to_formatted_string(&Locale::en)
+    );
+    println!(
+        "{} score: {} ({})",
+        reordered_file_path,
+        score_after.to_formatted_string(&Locale::en),
+        format_signed(score_after as i64 - score_before as i64)
+    );
+    Ok(())
+}
+
+fn normalize_command(normalize_matches: &ArgMatches) -> anyhow::Result<()> {
+    let input_data = parse_input_file(normalize_matches.value_of("input").expect("compulsory"))?;
+    let output_data = parse_output_file(normalize_matches.value_of("output").expect("compulsory"))?;
+    let normalized_file_path = normalize_matches
+        .value_of("normalized")
+        .expect("compulsory");
+    let (normalized, report) = normalize(&input_data, &output_data);
+    write_output_file(normalized_file_path, &normalized)?;
+    println!(
+        "{} score: {} ({})",
+        normalized_file_path,
+        report.score_after.to_formatted_string(&Locale::en),
+        format_signed(report.score_after as i64 - report.score_before as i64)
+    );
+    println!(
+        "size: {} -> {} bytes ({})",
+        report.bytes_before.to_formatted_string(&Locale::en),
+        report.bytes_after.to_formatted_string(&Locale::en),
+        format_signed(report.bytes_after as i64 - report.bytes_before as i64)
+    );
+    println!(
+        "intersection schedules: {} -> {}, rejected changes: {}",
+        output_data.intersection_schedules.len(),
+        normalized.intersection_schedules.len(),
+        report.rejected
+    );
+    Ok(())
+}
+
+fn stats_command(stats_matches: &ArgMatches) -> anyhow::Result<()> {
+    for input_file_path in stats_matches.values_of("input").expect("compulsory") {
+        let input_data = parse_input_file(input_file_path)?;
+        let input_stats = stats::input_stats(&input_data);
+        if stats_matches.is_present("json") {
+            println!("{}", stats::stats_json(input_file_path, &input_stats));
+        } else {
+            stats::print_stats(input_file_path, &input_stats);
+        }
+    }
+    Ok(())
+}
+
+fn explain_command(explain_matches: &ArgMatches) -> anyhow::Result<()> {
+    let input_data = parse_input_file(explain_matches.value_of("input").expect("compulsory"))?;
+    let output_data = parse_output_file(explain_matches.value_of("output").expect("compulsory"))?;
+    let car_id = value_t!(explain_matches, "car", usize)?;
+    let timeline = explain::explain_car(&input_data, &output_data, car_id)?;
+    explain::print_timeline(&input_data, &timeline);
+    Ok(())
+}
+
+fn graph_command(graph_matches: &ArgMatches) -> anyhow::Result<()> {
+    let input_data = parse_input_file(graph_matches.value_of("input").expect("compulsory"))?;
+    let report = match graph_matches.value_of("submission") {
+        Some(submission) => Some(simulate_report(
+            &input_data,
+            &parse_output_file(submission)?,
+        )),
+        None => None,
+    };
+    let format = value_t!(graph_matches, "format", GraphFormat)?;
+    let graph = export_graph(&input_data, report.as_ref(), format);
+    write(graph_matches.value_of("graph").expect("compulsory"), graph)?;
+    Ok(())
+}
+
+fn heatmap_command(heatmap_matches: &ArgMatches) -> anyhow::Result<()> {
+    let input_data = parse_input_file(heatmap_matches.value_of("input").expect("compulsory"))?;
+    let output_file_path = heatmap_matches.value_of("output").expect("compulsory");
+    let output_data = parse_output_file(output_file_path)?;
+    let html = heatmap::heatmap_report(&input_data, &output_data, output_file_path);
+    write(
+        heatmap_matches.value_of("report").expect("compulsory"),
+        html,
+    )?;
+    Ok(())
+}
+
+fn sweep_command(sweep_matches: &ArgMatches) -> anyhow::Result<()> {
+    let input_data = parse_input_file(sweep_matches.value_of("input").expect("compulsory"))?;
+    let output_data = parse_output_file(sweep_matches.value_of("output").expect("compulsory"))?;
+    let durations = values_t!(sweep_matches, "durations", Time)?;
+    sweep::print_sweep(
+        &input_data,
+        &sweep::duration_sweep(&input_data, &output_data, &durations),
+    );
+    Ok(())
+}
+
+fn sensitivity_command(sensitivity_matches: &ArgMatches) -> anyhow::Result<()> {
+    let input_data = parse_input_file(sensitivity_matches.value_of("input").expect("compulsory"))?;
+    let output_data =
+        parse_output_file(sensitivity_matches.value_of("output").expect("compulsory"))?;
+    let threads = threads(sensitivity_matches)?;
+    let limit = optional_usize(sensitivity_matches, "limit")?;
+    let gains = sensitivity::street_gains(&input_data, &output_data, threads);
+    sensitivity::print_gains(&input_data, &gains, limit);
+    Ok(())
+}
+
+fn impact_command(impact_matches: &ArgMatches) -> anyhow::Result<()> {
+    let input_data = parse_input_file(impact_matches.value_of("input").expect("compulsory"))?;
+    let output_data = parse_output_file(impact_matches.value_of("output").expect("compulsory"))?;
+    let top = optional_usize(impact_matches, "top")?;
+    let threads = threads(impact_matches)?;
+    let limit = optional_usize(impact_matches, "limit")?;
+    let report = impact::car_impacts(
+        &input_data,
+        &output_data,
+        top,
+        duration_secs(impact_matches, "time")?,
+        threads,
+    );
+    impact::print_impacts(&report, limit);
+    Ok(())
+}
+
+fn rank_command(rank_matches: &ArgMatches) -> anyhow::Result<()> {
+    let input_data = parse_input_file(rank_matches.value_of("input").expect("compulsory"))?;
+    let output_file_paths: Vec<&str> = rank_matches
+        .values_of("outputs")
+        .expect("compulsory")
+        .collect();
+    let threads = threads(rank_matches)?;
+    let ranked = rank::rank_outputs(&input_data, &output_file_paths, threads);
+    rank::print_ranking(&ranked);
+    if let Some(target) = rank_matches.value_of("copy_best") {
+        match ranked.first() {
+            Some(best) if best.score.is_ok() => {
+                copy(&best.output_file_path, target)?;
+                println!("copied {} to {}", best.output_file_path, target);
+            }
+            _ => bail!("no valid output to copy to {}", target),
+        }
+    }
+    Ok(())
+}
+
+fn merge_command(merge_matches: &ArgMatches) -> anyhow::Result<()> {
+    let input_data = parse_input_file(merge_matches.value_of("input").expect("compulsory"))?;
+    let output_file_paths: Vec<&str> = merge_matches
+        .values_of("outputs")
+        .expect("compulsory")
+        .collect();
+    let mut outputs = vec![];
+    for output_file_path in &output_file_paths {
+        let output_data = parse_output_file(output_file_path)?;
+        let errors = validate::validate_output(&input_data, &output_data);
+        if !errors.is_empty() {
+            bail!("{}: {}", output_file_path, errors.join(", "));
+        }
+        outputs.push(output_data);
+    }
+    let merged_file_path = merge_matches.value_of("merged").expect("compulsory");
+    let (merged, report) = merge::merge(&input_data, &outputs);
+    write_output_file(merged_file_path, &merged)?;
+    let log_file_path = match merge_matches.value_of("log") {
+        Some(log_file_path) => log_file_path.to_string(),
+        None => format!("{}.log", merged_file_path),
+    };
+    let log: String = report
+        .sources
+        .iter()
+        .map(|(intersection_id, source)| {
+            format!("{} {}\n", intersection_id, output_file_paths[*source])
+        })
+        .collect();
+    write(&log_file_path, log)?;
+    for (output_file_path, score) in output_file_paths.iter().zip(&report.scores) {
+        println!(
+            "{} score: {}",
+            output_file_path,
+            score.to_formatted_string(&Locale::en)
+        );
+    }
+    println!(
+        "{} score: {} ({} from {}), sources in {}",
+        merged_file_path,
+        report.score.to_formatted_string(&Locale::en),
+        format_signed(report.score as i64 - report.scores[report.base] as i64),
+        output_file_paths[report.base],
+        log_file_path
+    );
+    Ok(())
+}
+
+fn checkpoint_command(checkpoint_matches: &ArgMatches) -> anyhow::Result<()> {
+    let input_data = parse_input_file(checkpoint_matches.value_of("input").expect("compulsory"))?;
+    let output_data =
+        parse_output_file(checkpoint_matches.value_of("output").expect("compulsory"))?;
+    let checkpoint_file_path = checkpoint_matches
+        .value_of("checkpoint")
+        .expect("compulsory");
+    let at = value_t!(checkpoint_matches, "at", Time)?;
+    let simulator = Simulator::new(&input_data);
+    let mut state = simulator.initial_state(&output_data, &mut ());
+    simulator.resume(&output_data, &mut state, at, &mut ());
+    let checkpoint = checkpoint::Checkpoint {
+        fingerprint: checkpoint::fingerprint(&input_data, &output_data, &ScoringRules::default()),
+        state,
+    };
+    checkpoint::save_checkpoint(checkpoint_file_path, &checkpoint)?;
+    println!(
+        "{}: time {}, score so far: {}",
+        checkpoint_file_path,
+        checkpoint.state.time,
+        checkpoint.state.score.to_formatted_string(&Locale::en)
+    );
+    Ok(())
+}
+
+fn resume_command(resume_matches: &ArgMatches) -> anyhow::Result<()> {
+    let input_data = parse_input_file(resume_matches.value_of("input").expect("compulsory"))?;
+    let output_file_path = resume_matches.value_of("output").expect("compulsory");
+    let output_data = parse_output_file(output_file_path)?;
+    let checkpoint = checkpoint::load_checkpoint(
+        resume_matches.value_of("checkpoint").expect("compulsory"),
+        &input_data,
+    )?;
+    let simulator = Simulator::new(&input_data);
+    checkpoint::check_checkpoint(
+        &simulator,
+        &input_data,
+        &output_data,
+        &ScoringRules::default(),
+        &checkpoint,
+    )?;
+    let mut state = checkpoint.state;
+    simulator.resume(
+        &output_data,
+        &mut state,
+        input_data.header.simulation_duration,
+        &mut (),
+    );
+    println!(
+        "{} score: {}",
+        output_file_path,
+        state.score.to_formatted_string(&Locale::en)
+    );
+    Ok(())
+}
+
+fn query_command(query_matches: &ArgMatches) -> anyhow::Result<()> {
+    let input_data = parse_input_file(query_matches.value_of("input").expect("compulsory"))?;
+    let output_data = parse_output_file(query_matches.value_of("output").expect("compulsory"))?;
+    let time = value_t!(query_matches, "time", Time)?;
+    let (state, light_schedules) = query::state_at(&input_data, &output_data, time)?;
+    if query_matches.is_present("car") {
+        for car_id in values_t!(query_matches, "car", usize)? {
+            query::print_car_position(&input_data, &state, &light_schedules, car_id)?;
+        }
+    }
+    if let Some(street_names) = query_matches.values_of("street") {
+        for street_name in street_names {
+            query::print_street_queue(&input_data, &state, street_name)?;
+        }
+    }
+    Ok(())
+}
+
+fn timeline_command(timeline_matches: &ArgMatches) -> anyhow::Result<()> {
+    let input_data = parse_input_file(timeline_matches.value_of("input").expect("compulsory"))?;
+    let mut outputs = vec![];
+    for output_file_path in timeline_matches.values_of("output").expect("compulsory") {
+        outputs.push((output_file_path, parse_output_file(output_file_path)?));
+    }
+    let bucket_size = value_t!(timeline_matches, "bucket", Time)?;
+    if bucket_size == 0 {
+        bail!("bucket size must be positive");
+    }
+    let csv = timeline::timeline_csv(&input_data, &outputs, bucket_size);
+    match timeline_matches.value_of("csv") {
+        Some(csv_file_path) => write(csv_file_path, csv)?,
+        None => print!("{}", csv),
+    }
+    Ok(())
+}
+
+/// Scores each output file against its input file, the default command.
+fn score_command(matches: &ArgMatches) -> anyhow::Result<()> {
     let input_files = matches.values_of("input").expect("input files compulsory");
     let output_files = matches
         .values_of("output")
@@ -34,22 +468,77 @@ fn main() -> anyhow::Result<()> {
             input_files.len()
         );
     }
+    let input_output_files: Vec<(&str, &str)> = input_files.zip(output_files).collect();
+    let rules = ScoringRules {
+        crossing_delay: value_t!(matches, "crossing_delay", Time)?,
+        start_position: value_t!(matches, "start", StartPosition)?,
+        deadline: value_t!(matches, "deadline", Deadline)?,
+        bonus_multiplier: value_t!(matches, "bonus_multiplier", Score)?,
+    };
+    let official_rules = rules == ScoringRules::default();
+    if matches.is_present("watch") {
+        if !official_rules {
+            bail!("watch mode only supports the official scoring rules");
+        }
+        let interval = value_t!(matches, "interval", u64)?;
+        return watch::watch(&input_output_files, Duration::from_millis(interval));
+    }
+
+    let cancellation = cancel_on_ctrl_c()?;
     let mut total_score: Score = 0;
-    let input_output_files = input_files.zip(output_files);
     for (input_file_path, output_file_path) in input_output_files {
-        let path = PathBuf::from_str(output_file_path)?;
-        let output_content = read_to_string(path)?;
-        info!("parsing {}", output_file_path);
         // parsing output first since it is most likely to fail
-        let output_data = parse_output(&output_content)?;
-
-        let path = PathBuf::from_str(input_file_path)?;
-        let input_content = read_to_string(path)?;
-        info!("parsing {}", input_file_path);
-        let input_data = parse_input(&input_content)?;
+        let output_data = parse_output_file(output_file_path)?;
+        let input_data = parse_input_file(input_file_path)?;
+        if let Err(e) = rules.check(
+            input_data.header.simulation_duration,
+            input_data.header.bonus,
+        ) {
+            bail!("{}: {}", input_file_path, e);
+        }
 
-        let score = compute_score(&input_data, &output_data);
+        let progress = Arc::new(ProgressLine::new(
+            output_file_path,
+            input_data.header.simulation_duration,
+        ));
+        // Ctrl-C may come after the simulation reached its end
+        let interrupted =
+            |progress: &ProgressLine| progress.last().0 < input_data.header.simulation_duration;
+        let simulator = Simulator::new(&input_data)
+            .with_rules(rules)
+            .with_progress(progress.clone())
+            .with_cancellation(cancellation.clone());
+        let score = if official_rules {
+            let (score, starved_streets) =
+                score_with_starvation(&simulator, &input_data, &output_data);
+            progress.finish();
+            if !interrupted(&progress) {
+                warn_starvation(output_file_path, &input_data, &starved_streets);
+            }
+            score
+        } else {
+            let score = simulator.score(&output_data);
+            progress.finish();
+            score
+        };
         total_score += score;
+        if interrupted(&progress) {
+            let (time, finished_cars) = progress.last();
+            println!(
+                "{} partial score: {} (interrupted at t={}, {} cars finished)",
+                output_file_path,
+                score.to_formatted_string(&Locale::en),
+                time.to_formatted_string(&Locale::en),
+                finished_cars.to_formatted_string(&Locale::en)
+            );
+            if many {
+                println!(
+                    "partial total score: {}",
+                    total_score.to_formatted_string(&Locale::en)
+                );
+            }
+            bail!("interrupted");
+        }
         let formatted_score = score.to_formatted_string(&Locale::en);
         println!("{} score: {}", output_file_path, formatted_score);
     }
@@ -0,0 +1,124 @@
+use crate::data::{PInputData, PIntersectionSchedule, POutputData};
+use crate::score::{Score, Simulator};
+use log::debug;
+
+#[derive(Debug, PartialEq)]
+pub struct MergeReport {
+    // score of each submission
+    pub scores: Vec<Score>,
+    // index of the best submission, the merge starts from it
+    pub base: usize,
+    pub score: Score,
+    // (intersection id, index of the submission its schedule comes from) for each
+    // intersection scheduled in at least one submission
+    pub sources: Vec<(usize, usize)>,
+}
+
+fn schedules_by_intersection(
+    output: &POutputData,
+    intersections: usize,
+) -> Vec<Option<PIntersectionSchedule>> {
+    let mut schedules = vec![None; intersections];
+    for intersection_schedule in &output.intersection_schedules {
+        schedules[intersection_schedule.intersection_id] = Some(intersection_schedule.clone());
+    }
+    schedules
+}
+
+/// Starts from the best of `outputs` and, intersection by intersection, tries the schedule
+/// of every other submission, keeping it whenever the score improves.
+///
+/// A submission without a schedule for an intersection proposes to remove it.
+pub fn merge(input: &PInputData, outputs: &[POutputData]) -> (POutputData, MergeReport) {
+    assert!(!outputs.is_empty(), "nothing to merge");
+    let simulator = Simulator::new(input);
+    let scores: Vec<Score> = outputs
+        .iter()
+        .map(|output| simulator.score(output))
+        .collect();
+    // first best submission
+    let base = (0..outputs.len())
+        .max_by_key(|&i| (scores[i], std::cmp::Reverse(i)))
+        .expect("at least one output");
+    debug!("merging from submission {}: score {}", base, scores[base]);
+
+    let intersections = input.header.intersections;
+    let candidates: Vec<Vec<Option<PIntersectionSchedule>>> = outputs
+        .iter()
+        .map(|output| schedules_by_intersection(output, intersections))
+        .collect();
+    let mut schedules = candidates[base].clone();
+    // None for intersections no submission schedules
+    let mut sources: Vec<Option<usize>> = (0..intersections)
+        .map(|intersection_id| {
+            candidates
+                .iter()
+                .any(|submission| submission[intersection_id].is_some())
+                .then_some(base)
+        })
+        .collect();
+
+    let mut score = scores[base];
+    for intersection_id in 0..intersections {
+        if sources[intersection_id].is_none() {
+            continue;
+        }
+        for (i, submission) in candidates.iter().enumerate() {
+            if submission[intersection_id] == schedules[intersection_id] {
+                continue;
+            }
+            let previous = std::mem::replace(
+                &mut schedules[intersection_id],
+                submission[intersection_id].clone(),
+            );
+            let candidate_score = simulator.score(&POutputData::from_schedules(&schedules));
+            if candidate_score > score {
+                debug!(
+                    "intersection {} from submission {}: score {}",
+                    intersection_id, i, candidate_score
+                );
+                score = candidate_score;
+                sources[intersection_id] = Some(i);
+            } else {
+                schedules[intersection_id] = previous;
+            }
+        }
+    }
+
+    let report = MergeReport {
+        scores,
+        base,
+        score,
+        sources: sources
+            .iter()
+            .enumerate()
+            .filter_map(|(intersection_id, source)| Some((intersection_id, (*source)?)))
+            .collect(),
+    };
+    (POutputData::from_schedules(&schedules), report)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::test_data;
+    use crate::merge::merge;
+    use crate::score::compute_score;
+
+    #[test]
+    fn test_merge_example() {
+        let input_data = test_data::get_example_input_data();
+        let output_a = test_data::get_example_output_data();
+        let mut output_b = test_data::get_example_output_data();
+        // car 0 no longer waits at rue-d-amsterdam but rue-de-moscou is never green
+        output_b.intersection_schedules[0].light_schedules[0].1 = 1;
+        output_b.intersection_schedules.pop();
+        output_b.schedules = 2;
+        assert_eq!(compute_score(&input_data, &output_b), 0);
+        let (merged, report) = merge(&input_data, &[output_b, output_a]);
+        assert_eq!(report.scores, vec![0, 1002]);
+        assert_eq!(report.base, 1);
+        assert_eq!(report.score, compute_score(&input_data, &merged));
+        assert_eq!(report.sources, vec![(0, 1), (1, 0), (2, 1)]);
+        assert_eq!(report.score, 2002);
+    }
+}
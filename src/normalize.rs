@@ -0,0 +1,202 @@
+use crate::data::{PInputData, PIntersectionSchedule, POutputData};
+use crate::generator::street_traffic;
+use crate::score::{Score, Simulator};
+use crate::writer::format_output;
+use ahash::AHashMap;
+use log::debug;
+
+#[derive(Debug, PartialEq)]
+pub struct NormalizeReport {
+    pub score_before: Score,
+    pub score_after: Score,
+    // changes undone because they changed the score
+    pub rejected: usize,
+    pub bytes_before: usize,
+    pub bytes_after: usize,
+}
+
+// None when the whole intersection schedule can be removed
+fn normalized_schedule(
+    intersection_schedule: &PIntersectionSchedule,
+    used: &AHashMap<&str, bool>,
+) -> Option<PIntersectionSchedule> {
+    let mut light_schedules: Vec<(String, usize)> = vec![];
+    for (street_name, duration) in &intersection_schedule.light_schedules {
+        if *duration == 0 || !used.get(street_name.as_str()).copied().unwrap_or(false) {
+            continue;
+        }
+        // the simulator only keeps the last entry of a street, merge them all into the first
+        match light_schedules
+            .iter_mut()
+            .find(|(merged_street_name, _)| merged_street_name == street_name)
+        {
+            Some((_, merged_duration)) => *merged_duration += duration,
+            None => light_schedules.push((street_name.clone(), *duration)),
+        }
+    }
+    match light_schedules.len() {
+        0 => None,
+        1 => {
+            // a single street is always green whatever its duration
+            light_schedules[0].1 = 1;
+            Some(PIntersectionSchedule {
+                intersection_id: intersection_schedule.intersection_id,
+                incoming_streets: 1,
+                light_schedules,
+            })
+        }
+        incoming_streets => Some(PIntersectionSchedule {
+            intersection_id: intersection_schedule.intersection_id,
+            incoming_streets,
+            light_schedules,
+        }),
+    }
+}
+
+/// Tries `changes` at once, splits them in halves while the score changes.
+///
+/// Returns the number of rejected changes.
+fn apply_checked(
+    simulator: &Simulator,
+    schedules: &mut Vec<Option<PIntersectionSchedule>>,
+    changes: &[(usize, Option<PIntersectionSchedule>)],
+    score: Score,
+) -> usize {
+    if changes.is_empty() {
+        return 0;
+    }
+    let previous: Vec<Option<PIntersectionSchedule>> = changes
+        .iter()
+        .map(|(i, schedule)| std::mem::replace(&mut schedules[*i], schedule.clone()))
+        .collect();
+    let candidate_score = simulator.score(&POutputData::from_schedules(schedules));
+    if candidate_score == score {
+        return 0;
+    }
+    for ((i, _), schedule) in changes.iter().zip(previous) {
+        schedules[*i] = schedule;
+    }
+    if changes.len() == 1 {
+        debug!(
+            "rejected change of intersection schedule {}: score {}",
+            changes[0].0, candidate_score
+        );
+        return 1;
+    }
+    let (left, right) = changes.split_at(changes.len() / 2);
+    apply_checked(simulator, schedules, left, score)
+        + apply_checked(simulator, schedules, right, score)
+}
+
+/// Drops streets no car waits on, merges all entries of the same street and
+/// removes empty intersection schedules, keeping only changes that do not change the score.
+pub fn normalize(input: &PInputData, output: &POutputData) -> (POutputData, NormalizeReport) {
+    let simulator = Simulator::new(input);
+    let traffic = street_traffic(input);
+    let used = input
+        .body
+        .streets
+        .iter()
+        .enumerate()
+        .map(|(street_id, street)| (street.street_name.as_str(), traffic[street_id].cars > 0))
+        .collect::<AHashMap<&str, bool>>();
+
+    let mut schedules: Vec<Option<PIntersectionSchedule>> = output
+        .intersection_schedules
+        .iter()
+        .cloned()
+        .map(Some)
+        .collect();
+    let changes: Vec<(usize, Option<PIntersectionSchedule>)> = output
+        .intersection_schedules
+        .iter()
+        .enumerate()
+        .map(|(i, schedule)| (i, normalized_schedule(schedule, &used)))
+        .filter(|(i, schedule)| schedule.as_ref() != schedules[*i].as_ref())
+        .collect();
+
+    let score_before = simulator.score(output);
+    let rejected = apply_checked(&simulator, &mut schedules, &changes, score_before);
+    let normalized = POutputData::from_schedules(&schedules);
+    let score_after = simulator.score(&normalized);
+    let report = NormalizeReport {
+        score_before,
+        score_after,
+        rejected,
+        bytes_before: format_output(output).len(),
+        bytes_after: format_output(&normalized).len(),
+    };
+    (normalized, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{test_data, PIntersectionSchedule};
+    use crate::normalize::normalize;
+
+    #[test]
+    fn test_normalize_example() {
+        let input_data = test_data::get_example_input_data();
+        let mut output_data = test_data::get_example_output_data();
+        // rue-de-rome is the last street of car 0, no car waits at its end
+        output_data
+            .intersection_schedules
+            .push(PIntersectionSchedule {
+                intersection_id: 3,
+                incoming_streets: 1,
+                light_schedules: vec![("rue-de-rome".to_string(), 3)],
+            });
+        output_data.schedules = 4;
+        let (normalized, report) = normalize(&input_data, &output_data);
+        assert_eq!(report.score_before, 1002);
+        assert_eq!(report.score_after, 1002);
+        assert_eq!(report.rejected, 0);
+        assert!(report.bytes_after < report.bytes_before);
+        assert_eq!(normalized.schedules, 3);
+        assert_eq!(
+            normalized.intersection_schedules[0],
+            output_data.intersection_schedules[0]
+        );
+        // rue-de-londres is alone at intersection 0, always green
+        assert_eq!(
+            normalized.intersection_schedules[1].light_schedules,
+            vec![("rue-de-londres".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_normalize_non_adjacent_duplicates() {
+        let input_data = test_data::get_example_input_data();
+        let mut output_data = test_data::get_example_output_data();
+        output_data.intersection_schedules[0].light_schedules = vec![
+            ("rue-d-amsterdam".to_string(), 2),
+            ("rue-d-athenes".to_string(), 1),
+            ("rue-d-amsterdam".to_string(), 1),
+        ];
+        output_data.intersection_schedules[0].incoming_streets = 3;
+        let (normalized, report) = normalize(&input_data, &output_data);
+        assert_eq!(report.score_before, 1000);
+        assert_eq!(report.score_after, 1000);
+        assert_eq!(report.rejected, 0);
+        assert_eq!(
+            normalized.intersection_schedules[0].light_schedules,
+            vec![
+                ("rue-d-amsterdam".to_string(), 3),
+                ("rue-d-athenes".to_string(), 1)
+            ]
+        );
+        assert_eq!(normalized.intersection_schedules[0].incoming_streets, 2);
+
+        // only the last rue-d-amsterdam entry is green: merging would let car 0 cross at 1
+        // instead of 2, a different schedule
+        output_data.intersection_schedules[0].light_schedules[0].1 = 1;
+        let (normalized, report) = normalize(&input_data, &output_data);
+        assert_eq!(report.score_before, 1001);
+        assert_eq!(report.score_after, 1001);
+        assert_eq!(report.rejected, 1);
+        assert_eq!(
+            normalized.intersection_schedules[0],
+            output_data.intersection_schedules[0]
+        );
+    }
+}
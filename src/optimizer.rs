@@ -0,0 +1,414 @@
+use crate::analysis::unreachable_cars;
+use crate::data::{PInputData, PIntersectionSchedule, POutputData};
+use crate::generator::street_traffic;
+use crate::progress::CancellationToken;
+use crate::report::format_signed;
+use crate::score::{compute_score, Score, Simulator};
+use crate::validate::validate_output;
+use crate::writer::write_output_file;
+use anyhow::bail;
+use log::debug;
+use num_format::{Locale, ToFormattedString};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Method {
+    /// keep candidates scoring at least as much as the current schedule
+    HillClimbing,
+    /// also keep worse candidates with a probability decreasing over time
+    Annealing,
+}
+
+impl FromStr for Method {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hill-climbing" => Ok(Method::HillClimbing),
+            "annealing" => Ok(Method::Annealing),
+            _ => bail!("unknown method: {}", s),
+        }
+    }
+}
+
+pub const METHODS: &[&str] = &["hill-climbing", "annealing"];
+
+#[derive(Debug, Clone)]
+pub struct OptimizerConfig {
+    pub method: Method,
+    pub time_budget: Duration,
+    // stops after this many iterations even if time remains
+    pub max_iterations: Option<usize>,
+    pub seed: u64,
+    // initial annealing temperature, in points
+    pub temperature: f64,
+    // faster but optimistic scores, cf `Simulator::skip_unreachable`
+    pub skip_unreachable: bool,
+}
+
+/// Schedules indexed by intersection id, empty for intersections without schedule.
+///
+/// Empty schedules are ignored by the simulator and dropped when written.
+fn working_schedules(input: &PInputData, output: &POutputData) -> POutputData {
+    let mut intersection_schedules: Vec<PIntersectionSchedule> = (0..input.header.intersections)
+        .map(|intersection_id| PIntersectionSchedule {
+            intersection_id,
+            incoming_streets: 0,
+            light_schedules: vec![],
+        })
+        .collect();
+    for intersection_schedule in &output.intersection_schedules {
+        intersection_schedules[intersection_schedule.intersection_id] = PIntersectionSchedule {
+            intersection_id: intersection_schedule.intersection_id,
+            incoming_streets: intersection_schedule.light_schedules.len(),
+            light_schedules: intersection_schedule.light_schedules.clone(),
+        };
+    }
+    POutputData {
+        schedules: intersection_schedules.len(),
+        intersection_schedules,
+    }
+}
+
+fn submission(working: &POutputData) -> POutputData {
+    let intersection_schedules: Vec<PIntersectionSchedule> = working
+        .intersection_schedules
+        .iter()
+        .filter(|schedule| !schedule.light_schedules.is_empty())
+        .map(|schedule| PIntersectionSchedule {
+            intersection_id: schedule.intersection_id,
+            incoming_streets: schedule.light_schedules.len(),
+            light_schedules: schedule.light_schedules.clone(),
+        })
+        .collect();
+    POutputData {
+        schedules: intersection_schedules.len(),
+        intersection_schedules,
+    }
+}
+
+// street names cars wait on, per intersection
+fn candidate_streets(input: &PInputData) -> Vec<Vec<String>> {
+    let traffic = street_traffic(input);
+    let mut candidates: Vec<Vec<String>> = vec![vec![]; input.header.intersections];
+    for (street_id, street) in input.body.streets.iter().enumerate() {
+        if traffic[street_id].cars > 0 {
+            candidates[street.intersection_end].push(street.street_name.clone());
+        }
+    }
+    candidates
+}
+
+/// Applies a random move to `schedule`, returns false if no move applies.
+fn random_move(
+    rng: &mut SmallRng,
+    schedule: &mut PIntersectionSchedule,
+    candidates: &[String],
+    max_duration: usize,
+) -> bool {
+    let light_schedules = &mut schedule.light_schedules;
+    match rng.gen_range(0..4) {
+        // change a duration
+        0 if !light_schedules.is_empty() => {
+            let i = rng.gen_range(0..light_schedules.len());
+            let duration = light_schedules[i].1;
+            let new_duration = if rng.gen_bool(0.5) {
+                duration + 1
+            } else {
+                duration.saturating_sub(1)
+            }
+            .clamp(1, max_duration);
+            light_schedules[i].1 = new_duration;
+            new_duration != duration
+        }
+        // swap order
+        1 if light_schedules.len() >= 2 => {
+            let i = rng.gen_range(0..light_schedules.len());
+            let j = (i + rng.gen_range(1..light_schedules.len())) % light_schedules.len();
+            light_schedules.swap(i, j);
+            true
+        }
+        // drop a street
+        2 if !light_schedules.is_empty() => {
+            let i = rng.gen_range(0..light_schedules.len());
+            light_schedules.remove(i);
+            true
+        }
+        // add a street
+        3 => {
+            let missing: Vec<&String> = candidates
+                .iter()
+                .filter(|name| {
+                    !light_schedules
+                        .iter()
+                        .any(|(scheduled, _)| scheduled == *name)
+                })
+                .collect();
+            if missing.is_empty() {
+                return false;
+            }
+            let name = missing[rng.gen_range(0..missing.len())].clone();
+            let i = rng.gen_range(0..=light_schedules.len());
+            light_schedules.insert(i, (name, 1));
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Score of the written submission: with `skip_unreachable`, the search only estimates
+/// scores, the submission is scored again with every car.
+fn submission_score(
+    input: &PInputData,
+    config: &OptimizerConfig,
+    best: &POutputData,
+    best_score: Score,
+    output_file_path: &str,
+) -> Score {
+    if !config.skip_unreachable {
+        return best_score;
+    }
+    let score = compute_score(input, best);
+    println!(
+        "{} score: {}",
+        output_file_path,
+        score.to_formatted_string(&Locale::en)
+    );
+    score
+}
+
+/// Improves `initial` until the time budget or the iterations are spent, or `cancellation`
+/// is cancelled.
+///
+/// Each improvement is written to `output_file_path`, the best score is returned.
+pub fn optimize(
+    input: &PInputData,
+    initial: &POutputData,
+    config: &OptimizerConfig,
+    output_file_path: &str,
+    cancellation: &CancellationToken,
+) -> anyhow::Result<Score> {
+    let errors = validate_output(input, initial);
+    if !errors.is_empty() {
+        bail!("invalid initial output: {}", errors.join(", "));
+    }
+    let start = Instant::now();
+    let simulator = if config.skip_unreachable {
+        Simulator::new(input).skip_unreachable(&unreachable_cars(input))
+    } else {
+        Simulator::new(input)
+    };
+    let mut rng = SmallRng::seed_from_u64(config.seed);
+    let candidates = candidate_streets(input);
+    let intersections: Vec<usize> = (0..input.header.intersections)
+        .filter(|&intersection_id| !candidates[intersection_id].is_empty())
+        .collect();
+    let max_duration = input.header.simulation_duration.max(1);
+
+    let mut working = working_schedules(input, initial);
+    let initial_score = simulator.score(&working);
+    let mut current_score = initial_score;
+    let mut best_score = initial_score;
+    let mut best = submission(&working);
+    write_output_file(output_file_path, &best)?;
+    // cars skipped by the simulator make scores optimistic
+    let score_label = if config.skip_unreachable {
+        "estimated score"
+    } else {
+        "score"
+    };
+    println!(
+        "initial {}: {}",
+        score_label,
+        initial_score.to_formatted_string(&Locale::en)
+    );
+    if intersections.is_empty() {
+        return Ok(submission_score(
+            input,
+            config,
+            &best,
+            best_score,
+            output_file_path,
+        ));
+    }
+
+    let mut iterations: usize = 0;
+    let mut accepted: usize = 0;
+    while start.elapsed() < config.time_budget
+        && config.max_iterations.is_none_or(|max| iterations < max)
+        && !cancellation.is_cancelled()
+    {
+        iterations += 1;
+        let intersection_id = intersections[rng.gen_range(0..intersections.len())];
+        let schedule = &mut working.intersection_schedules[intersection_id];
+        let previous = schedule.light_schedules.clone();
+        if !random_move(
+            &mut rng,
+            schedule,
+            &candidates[intersection_id],
+            max_duration,
+        ) {
+            schedule.light_schedules = previous;
+            continue;
+        }
+        let score = simulator.score(&working);
+        let delta = score as f64 - current_score as f64;
+        let accept = match config.method {
+            Method::HillClimbing => delta >= 0.0,
+            Method::Annealing => {
+                let remaining =
+                    1.0 - start.elapsed().as_secs_f64() / config.time_budget.as_secs_f64();
+                let temperature = config.temperature * remaining.max(0.0);
+                delta >= 0.0
+                    || (temperature > 0.0 && rng.gen::<f64>() < (delta / temperature).exp())
+            }
+        };
+        if !accept {
+            working.intersection_schedules[intersection_id].light_schedules = previous;
+            continue;
+        }
+        accepted += 1;
+        current_score = score;
+        debug!("iteration {}: accepted score {}", iterations, score);
+        if current_score > best_score {
+            best_score = current_score;
+            best = submission(&working);
+            write_output_file(output_file_path, &best)?;
+            println!(
+                "[{:.1}s] iteration {}: {} {}: {} ({})",
+                start.elapsed().as_secs_f64(),
+                iterations,
+                output_file_path,
+                score_label,
+                best_score.to_formatted_string(&Locale::en),
+                format_signed(best_score as i64 - initial_score as i64)
+            );
+        }
+    }
+    if cancellation.is_cancelled() {
+        println!("interrupted after {:.1}s", start.elapsed().as_secs_f64());
+    }
+    println!(
+        "{} iterations, {} accepted, best {}: {} ({})",
+        iterations,
+        accepted,
+        score_label,
+        best_score.to_formatted_string(&Locale::en),
+        format_signed(best_score as i64 - initial_score as i64)
+    );
+    Ok(submission_score(
+        input,
+        config,
+        &best,
+        best_score,
+        output_file_path,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::test_data;
+    use crate::generator::{generate, Strategy};
+    use crate::optimizer::{optimize, Method, OptimizerConfig};
+    use crate::parser::parse_output_file;
+    use crate::progress::CancellationToken;
+    use crate::score::compute_score;
+    use std::time::Duration;
+
+    #[test]
+    fn test_optimize_example() {
+        let input_data = test_data::get_example_input_data();
+        // 1,001: car 0 waits for rue-d-athenes
+        let initial = generate(&input_data, Strategy::Uniform);
+        let path = std::env::temp_dir().join(format!(
+            "ghc2021-score-test-optimize-{}.out",
+            std::process::id()
+        ));
+        let path = path.to_str().unwrap();
+        // bounded by iterations only, the result does not depend on the machine speed
+        let config = OptimizerConfig {
+            method: Method::HillClimbing,
+            time_budget: Duration::from_secs(3600),
+            max_iterations: Some(100),
+            seed: 42,
+            temperature: 0.0,
+            skip_unreachable: false,
+        };
+        let best_score = optimize(
+            &input_data,
+            &initial,
+            &config,
+            path,
+            &CancellationToken::default(),
+        )
+        .unwrap();
+        assert_eq!(best_score, 2002);
+        let written = parse_output_file(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(compute_score(&input_data, &written), best_score);
+    }
+
+    #[test]
+    fn test_optimize_skip_unreachable() {
+        let input_data = test_data::get_example_input_data();
+        let initial = generate(&input_data, Strategy::Uniform);
+        let path = std::env::temp_dir().join(format!(
+            "ghc2021-score-test-optimize-skip-{}.out",
+            std::process::id()
+        ));
+        let path = path.to_str().unwrap();
+        let config = OptimizerConfig {
+            method: Method::HillClimbing,
+            time_budget: Duration::from_secs(3600),
+            max_iterations: Some(100),
+            seed: 42,
+            temperature: 0.0,
+            skip_unreachable: true,
+        };
+        let best_score = optimize(
+            &input_data,
+            &initial,
+            &config,
+            path,
+            &CancellationToken::default(),
+        )
+        .unwrap();
+        let written = parse_output_file(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        // the official score of the written submission, not the estimate
+        assert_eq!(compute_score(&input_data, &written), best_score);
+    }
+
+    #[test]
+    fn test_optimize_invalid_initial() {
+        let input_data = test_data::get_example_input_data();
+        let mut initial = test_data::get_example_output_data();
+        initial.intersection_schedules[0].intersection_id = 4;
+        let config = OptimizerConfig {
+            method: Method::HillClimbing,
+            time_budget: Duration::from_secs(3600),
+            max_iterations: Some(1),
+            seed: 42,
+            temperature: 0.0,
+            skip_unreachable: false,
+        };
+        let error = optimize(
+            &input_data,
+            &initial,
+            &config,
+            "unused.out",
+            &CancellationToken::default(),
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("intersection 4: unknown intersection"),
+            "{}",
+            error
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+/// Number of threads to use when none is given.
+pub(crate) fn default_threads() -> usize {
+    thread::available_parallelism().map_or(1, |threads| threads.get())
+}
+
+/// Applies `f` to each item on `threads` threads, results are in `items` order.
+///
+/// Threads pick the next item from a shared counter so slow items do not stall a whole chunk.
+pub(crate) fn parallel_map<T, R, F>(items: &[T], threads: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    parallel_map_until(items, threads, None, f)
+        .into_iter()
+        .map(|result| result.expect("every item is processed without deadline"))
+        .collect()
+}
+
+/// Same as `parallel_map` but items not started before `deadline` are skipped (`None`).
+pub(crate) fn parallel_map_until<T, R, F>(
+    items: &[T],
+    threads: usize,
+    deadline: Option<Instant>,
+    f: F,
+) -> Vec<Option<R>>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new((0..items.len()).map(|_| None).collect());
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1).min(items.len()) {
+            scope.spawn(|| loop {
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    break;
+                }
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= items.len() {
+                    break;
+                }
+                let result = f(&items[i]);
+                results.lock().expect("no panic while locked")[i] = Some(result);
+            });
+        }
+    });
+    results.into_inner().expect("no panic while locked")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parallel::{parallel_map, parallel_map_until};
+    use std::time::Instant;
+
+    #[test]
+    fn test_parallel_map_keeps_order() {
+        let items: Vec<usize> = (0..100).collect();
+        let squares = parallel_map(&items, 4, |i| i * i);
+        assert_eq!(squares, items.iter().map(|i| i * i).collect::<Vec<usize>>());
+        assert!(parallel_map(&[] as &[usize], 4, |i| *i).is_empty());
+    }
+
+    #[test]
+    fn test_parallel_map_until_deadline() {
+        let items: Vec<usize> = (0..10).collect();
+        let results = parallel_map_until(&items, 2, Some(Instant::now()), |i| *i);
+        assert!(results.iter().all(|result| result.is_none()));
+    }
+}
@@ -2,18 +2,20 @@ use crate::data::{
     InputHeader, PCarPath, PInputBody, PInputData, PIntersectionSchedule, POutputData, PStreet,
 };
 use anyhow::bail;
+use log::info;
 use nom::bytes::complete::{tag, take_while1, take_while_m_n};
 use nom::combinator::{map_res, verify};
 use nom::error::{context, convert_error, VerboseError};
 use nom::multi::{many_m_n, separated_list1};
 use nom::sequence::{terminated, tuple};
 use nom::IResult;
+use std::fs::read_to_string;
 
 pub(crate) type N = usize;
 pub(crate) type Res<T, U> = IResult<T, U, VerboseError<T>>;
 
 fn number(input: &str) -> Res<&str, &str> {
-    context("number", take_while1(|c: char| c.is_digit(10)))(input)
+    context("number", take_while1(|c: char| c.is_ascii_digit()))(input)
 }
 
 fn positive_number(input: &str) -> Res<&str, N> {
@@ -188,6 +190,18 @@ pub fn parse_output(s: &str) -> anyhow::Result<POutputData> {
     }
 }
 
+pub fn parse_input_file(path: &str) -> anyhow::Result<PInputData> {
+    let content = read_to_string(path)?;
+    info!("parsing {}", path);
+    parse_input(&content)
+}
+
+pub fn parse_output_file(path: &str) -> anyhow::Result<POutputData> {
+    let content = read_to_string(path)?;
+    info!("parsing {}", path);
+    parse_output(&content)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::data::test_data;
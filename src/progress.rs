@@ -0,0 +1,164 @@
+use crate::score::Time;
+use num_format::{Locale, ToFormattedString};
+use std::io::{stderr, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Simulated seconds between two progress notifications or cancellation checks.
+pub(crate) const PROGRESS_INTERVAL: Time = 256;
+
+/// Notified of the progress of long simulations, cf `Simulator::with_progress`.
+pub(crate) trait Progress: Send + Sync {
+    fn on_progress(&self, time: Time, finished_cars: usize);
+}
+
+/// Shared flag asking simulations and the optimizer to stop as soon as possible.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub(crate) fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Cancels the returned token on the first Ctrl-C, exits on the second one.
+pub(crate) fn cancel_on_ctrl_c() -> anyhow::Result<CancellationToken> {
+    let cancellation = CancellationToken::default();
+    let handler_cancellation = cancellation.clone();
+    ctrlc::set_handler(move || {
+        if handler_cancellation.is_cancelled() {
+            std::process::exit(130);
+        }
+        handler_cancellation.cancel();
+    })?;
+    Ok(cancellation)
+}
+
+/// Progress line on stderr, refreshed at most every 100ms and only if stderr is a terminal.
+pub(crate) struct ProgressLine {
+    label: String,
+    simulation_duration: Time,
+    visible: bool,
+    // last printed time and (simulated time, finished cars) of the last notification
+    state: Mutex<(Option<Instant>, Time, usize)>,
+}
+
+const REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+
+impl ProgressLine {
+    pub(crate) fn new(label: &str, simulation_duration: Time) -> Self {
+        ProgressLine {
+            label: label.to_string(),
+            simulation_duration,
+            visible: stderr().is_terminal(),
+            state: Mutex::new((None, 0, 0)),
+        }
+    }
+
+    /// Simulated time and finished cars of the last notification.
+    pub(crate) fn last(&self) -> (Time, usize) {
+        let state = self.state.lock().expect("progress line poisoned");
+        (state.1, state.2)
+    }
+
+    /// Clears the line before the results are printed.
+    pub(crate) fn finish(&self) {
+        let state = self.state.lock().expect("progress line poisoned");
+        if self.visible && state.0.is_some() {
+            eprint!("\r\x1b[K");
+        }
+    }
+}
+
+impl Progress for ProgressLine {
+    fn on_progress(&self, time: Time, finished_cars: usize) {
+        let mut state = self.state.lock().expect("progress line poisoned");
+        state.1 = time;
+        state.2 = finished_cars;
+        if !self.visible
+            || state
+                .0
+                .is_some_and(|printed| printed.elapsed() < REFRESH_INTERVAL)
+        {
+            return;
+        }
+        state.0 = Some(Instant::now());
+        eprint!(
+            "\r\x1b[K{}: t={}/{} ({}%), {} cars finished",
+            self.label,
+            time.to_formatted_string(&Locale::en),
+            self.simulation_duration.to_formatted_string(&Locale::en),
+            time * 100 / self.simulation_duration.max(1),
+            finished_cars.to_formatted_string(&Locale::en)
+        );
+        stderr().flush().ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::test_data;
+    use crate::parser::{parse_input_file, parse_output_file};
+    use crate::progress::{CancellationToken, Progress, PROGRESS_INTERVAL};
+    use crate::score::{Simulator, Time};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct RecordedProgress(Mutex<Vec<(Time, usize)>>);
+
+    impl Progress for RecordedProgress {
+        fn on_progress(&self, time: Time, finished_cars: usize) {
+            self.0.lock().unwrap().push((time, finished_cars));
+        }
+    }
+
+    #[test]
+    fn test_progress_and_cancellation() {
+        // 676 seconds, 1,000 cars
+        let input_data = parse_input_file("res/e.txt").unwrap();
+        let output_data = parse_output_file("out/e.out").unwrap();
+        let progress = Arc::new(RecordedProgress::default());
+        let score = Simulator::new(&input_data)
+            .with_progress(progress.clone())
+            .score(&output_data);
+        assert_eq!(score, 710_095);
+        let recorded = progress.0.lock().unwrap().clone();
+        let times: Vec<Time> = recorded.iter().map(|&(time, _)| time).collect();
+        assert_eq!(times, vec![256, 512, 676]);
+        assert!(recorded.windows(2).all(|w| w[0].1 <= w[1].1));
+
+        let cancellation = CancellationToken::default();
+        cancellation.cancel();
+        let progress = Arc::new(RecordedProgress::default());
+        let partial_score = Simulator::new(&input_data)
+            .with_progress(progress.clone())
+            .with_cancellation(cancellation)
+            .score(&output_data);
+        assert!(partial_score < score);
+        assert_eq!(
+            progress.0.lock().unwrap().as_slice(),
+            &recorded[..1],
+            "stops after {} seconds",
+            PROGRESS_INTERVAL
+        );
+    }
+
+    #[test]
+    fn test_progress_reaches_the_end() {
+        let mut input_data = test_data::get_example_input_data();
+        input_data.header.simulation_duration = 1_000;
+        let output_data = test_data::get_example_output_data();
+        let progress = Arc::new(RecordedProgress::default());
+        Simulator::new(&input_data)
+            .with_progress(progress.clone())
+            .score(&output_data);
+        // both cars finished before 256, the rest of the simulation is skipped
+        assert_eq!(progress.0.lock().unwrap().as_slice(), &[(1_000, 2)]);
+    }
+}
@@ -0,0 +1,238 @@
+use crate::data::{PInputData, POutputData};
+use crate::lights::LightSchedules;
+use crate::score::{Action, CarId, SimulationState, Simulator, StreetId, Time};
+use anyhow::bail;
+
+#[derive(Debug, PartialEq)]
+pub enum CarPosition {
+    /// `position` cars ahead in the queue at the light of `street_id`, crosses the
+    /// intersection at `crossing_time` unless the light is never green
+    Waiting {
+        street_id: StreetId,
+        position: usize,
+        crossing_time: Option<Time>,
+    },
+    /// `distance` seconds into `street_id`, at its end at `arrival_time`
+    Driving {
+        street_id: StreetId,
+        distance: Time,
+        arrival_time: Time,
+    },
+    Finished {
+        time: Time,
+    },
+}
+
+/// Simulation state at the beginning of second `time`, before lights let cars through,
+/// and light schedules of `output`.
+///
+/// `time` is at most the simulation duration.
+pub(crate) fn state_at(
+    input: &PInputData,
+    output: &POutputData,
+    time: Time,
+) -> anyhow::Result<(SimulationState, LightSchedules)> {
+    if time > input.header.simulation_duration {
+        bail!(
+            "time {} after the end of the simulation at {}",
+            time,
+            input.header.simulation_duration
+        );
+    }
+    let simulator = Simulator::new(input);
+    let mut state = simulator.initial_state(output, &mut ());
+    simulator.resume(output, &mut state, time, &mut ());
+    // the simulation may stop early once no car can move any more
+    state.time = time;
+    Ok((state, simulator.light_schedules(output)))
+}
+
+pub(crate) fn car_position(
+    state: &SimulationState,
+    light_schedules: &LightSchedules,
+    car_id: CarId,
+) -> anyhow::Result<CarPosition> {
+    let car_tracker = match state.car_trackers.get(car_id) {
+        Some(car_tracker) => car_tracker,
+        None => bail!(
+            "unknown car {}: there are {} cars",
+            car_id,
+            state.car_trackers.len()
+        ),
+    };
+    match car_tracker.action {
+        Action::Waiting(street_id) => {
+            let position = state.street_queues[street_id]
+                .iter()
+                .position(|&tracker_index| tracker_index == car_id)
+                .expect("waiting cars are queued");
+            // each car ahead crosses on its own green second
+            let mut crossing_time = light_schedules.next_green(street_id, state.time);
+            for _ in 0..position {
+                crossing_time =
+                    crossing_time.and_then(|time| light_schedules.next_green(street_id, time + 1));
+            }
+            Ok(CarPosition::Waiting {
+                street_id,
+                position,
+                crossing_time,
+            })
+        }
+        Action::Driving(street_id, street_length) => {
+            // the car moved by one as soon as it crossed the previous intersection
+            let distance = car_tracker.distance_current_street.saturating_sub(1);
+            Ok(CarPosition::Driving {
+                street_id,
+                distance,
+                arrival_time: state.time + street_length - distance,
+            })
+        }
+        Action::Finished(time) => Ok(CarPosition::Finished { time: time + 1 }),
+    }
+}
+
+/// Cars waiting at the light of `street_id`, first in line first.
+pub fn street_queue(state: &SimulationState, street_id: StreetId) -> Vec<CarId> {
+    state
+        .street_queues
+        .get(street_id)
+        .map(|street_queue| {
+            street_queue
+                .iter()
+                .map(|&tracker_index| state.car_trackers[tracker_index].id)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub(crate) fn print_car_position(
+    input: &PInputData,
+    state: &SimulationState,
+    light_schedules: &LightSchedules,
+    car_id: CarId,
+) -> anyhow::Result<()> {
+    let streets = &input.body.streets;
+    match car_position(state, light_schedules, car_id)? {
+        CarPosition::Waiting {
+            street_id,
+            position,
+            crossing_time,
+        } => println!(
+            "t={}: car {} waits at the light of {} (intersection {}), {} cars ahead, {}",
+            state.time,
+            car_id,
+            streets[street_id].street_name,
+            streets[street_id].intersection_end,
+            position,
+            match crossing_time {
+                Some(time) if time < input.header.simulation_duration =>
+                    format!("crosses at t={}", time),
+                _ => "does not cross before the end of the simulation".to_string(),
+            }
+        ),
+        CarPosition::Driving {
+            street_id,
+            distance,
+            arrival_time,
+        } => println!(
+            "t={}: car {} drives {} ({}s), {}s driven, at its end at t={}",
+            state.time,
+            car_id,
+            streets[street_id].street_name,
+            streets[street_id].street_length,
+            distance,
+            arrival_time
+        ),
+        CarPosition::Finished { time } => {
+            println!("t={}: car {} finished at t={}", state.time, car_id, time)
+        }
+    }
+    Ok(())
+}
+
+pub fn print_street_queue(
+    input: &PInputData,
+    state: &SimulationState,
+    street_name: &str,
+) -> anyhow::Result<()> {
+    let street_id_of_name = input.street_id_of_name();
+    let street_id = match street_id_of_name.get(street_name) {
+        Some(&street_id) => street_id,
+        None => bail!("unknown street {}", street_name),
+    };
+    let cars: Vec<String> = street_queue(state, street_id)
+        .iter()
+        .map(|car_id| car_id.to_string())
+        .collect();
+    if cars.is_empty() {
+        println!(
+            "t={}: no car waits at the light of {}",
+            state.time, street_name
+        );
+    } else {
+        println!(
+            "t={}: cars waiting at the light of {}, first in line first: {}",
+            state.time,
+            street_name,
+            cars.join(" ")
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::test_data;
+    use crate::query::{car_position, state_at, street_queue, CarPosition};
+
+    #[test]
+    fn test_query_example() {
+        let input_data = test_data::get_example_input_data();
+        let output_data = test_data::get_example_output_data();
+        let (state, light_schedules) = state_at(&input_data, &output_data, 0).unwrap();
+        assert_eq!(street_queue(&state, 0), vec![0]);
+        assert_eq!(street_queue(&state, 2), vec![1]);
+        // rue-de-londres is green at 0 and 1
+        assert_eq!(
+            car_position(&state, &light_schedules, 0).unwrap(),
+            CarPosition::Waiting {
+                street_id: 0,
+                position: 0,
+                crossing_time: Some(0),
+            }
+        );
+        let (state, light_schedules) = state_at(&input_data, &output_data, 2).unwrap();
+        // car 0 reached rue-d-amsterdam at 1, crosses at 2
+        assert_eq!(
+            car_position(&state, &light_schedules, 0).unwrap(),
+            CarPosition::Waiting {
+                street_id: 1,
+                position: 0,
+                crossing_time: Some(2),
+            }
+        );
+        // car 1 crossed at 0, rue-de-moscou takes 3 seconds
+        assert_eq!(
+            car_position(&state, &light_schedules, 1).unwrap(),
+            CarPosition::Driving {
+                street_id: 4,
+                distance: 2,
+                arrival_time: 3,
+            }
+        );
+        let (state, light_schedules) = state_at(&input_data, &output_data, 6).unwrap();
+        assert_eq!(
+            car_position(&state, &light_schedules, 1).unwrap(),
+            CarPosition::Finished { time: 4 }
+        );
+        assert!(car_position(&state, &light_schedules, 2).is_err());
+    }
+
+    #[test]
+    fn test_state_after_the_end() {
+        let input_data = test_data::get_example_input_data();
+        let output_data = test_data::get_example_output_data();
+        assert!(state_at(&input_data, &output_data, 6).is_ok());
+        assert!(state_at(&input_data, &output_data, 7).is_err());
+    }
+}
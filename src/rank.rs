@@ -0,0 +1,98 @@
+use crate::data::PInputData;
+use crate::parallel::parallel_map;
+use crate::parser::parse_output_file;
+use crate::score::{Score, Simulator};
+use crate::validate::validate_output;
+use num_format::{Locale, ToFormattedString};
+
+const MAX_PRINTED_ERRORS: usize = 10;
+
+#[derive(Debug, PartialEq)]
+pub struct RankedOutput {
+    pub output_file_path: String,
+    // parse or validation errors of invalid outputs
+    pub score: Result<Score, Vec<String>>,
+}
+
+/// Parses, validates and scores each output file on `threads` threads.
+///
+/// Valid outputs come first, best score first, then invalid outputs.
+pub fn rank_outputs(
+    input: &PInputData,
+    output_file_paths: &[&str],
+    threads: usize,
+) -> Vec<RankedOutput> {
+    let simulator = Simulator::new(input);
+    let mut ranked = parallel_map(output_file_paths, threads, |&output_file_path| {
+        let score = match parse_output_file(output_file_path) {
+            Ok(output) => {
+                let errors = validate_output(input, &output);
+                if errors.is_empty() {
+                    Ok(simulator.score(&output))
+                } else {
+                    Err(errors)
+                }
+            }
+            Err(e) => Err(vec![e.to_string()]),
+        };
+        RankedOutput {
+            output_file_path: output_file_path.to_string(),
+            score,
+        }
+    });
+    ranked.sort_by_key(|ranked_output| match ranked_output.score {
+        Ok(score) => (false, std::cmp::Reverse(score)),
+        Err(_) => (true, std::cmp::Reverse(0)),
+    });
+    ranked
+}
+
+pub fn print_ranking(ranked: &[RankedOutput]) {
+    for (rank, ranked_output) in ranked.iter().enumerate() {
+        match &ranked_output.score {
+            Ok(score) => println!(
+                "{} {} {}",
+                rank + 1,
+                score.to_formatted_string(&Locale::en),
+                ranked_output.output_file_path
+            ),
+            Err(errors) => {
+                println!("- invalid {}", ranked_output.output_file_path);
+                for error in errors.iter().take(MAX_PRINTED_ERRORS) {
+                    for line in error.lines() {
+                        println!("    {}", line);
+                    }
+                }
+                if errors.len() > MAX_PRINTED_ERRORS {
+                    println!(
+                        "    ... and {} more errors",
+                        (errors.len() - MAX_PRINTED_ERRORS).to_formatted_string(&Locale::en)
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse_input_file;
+    use crate::rank::rank_outputs;
+
+    #[test]
+    fn test_rank_outputs() {
+        let input_data = parse_input_file("res/a.txt").unwrap();
+        let ranked = rank_outputs(
+            &input_data,
+            &["res/a.txt", "out/missing.out", "out/a.out"],
+            2,
+        );
+        assert_eq!(ranked[0].output_file_path, "out/a.out");
+        assert_eq!(ranked[0].score, Ok(2002));
+        // invalid outputs keep their order
+        assert_eq!(ranked[1].output_file_path, "res/a.txt");
+        assert!(ranked[1].score.is_err());
+        assert_eq!(ranked[2].output_file_path, "out/missing.out");
+        assert!(ranked[2].score.is_err());
+    }
+}
@@ -0,0 +1,224 @@
+use crate::data::{PInputData, POutputData};
+use crate::score::{simulate, CarId, Observer, Score, StreetId, Time};
+use num_format::{Locale, ToFormattedString};
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub(crate) struct CarStats {
+    pub(crate) finish_time: Option<Time>,
+    pub(crate) points: Score,
+    pub(crate) waiting_time: Time,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub(crate) struct StreetStats {
+    pub(crate) cars_passed: usize,
+    // cars still queued at the end of the simulation wait until the end
+    pub(crate) waiting_time: Time,
+    pub(crate) max_queue_length: usize,
+}
+
+/// Per car and per street outcome of a simulation, indexed by car id and street id.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct SimulationReport {
+    pub(crate) score: Score,
+    pub(crate) cars: Vec<CarStats>,
+    pub(crate) streets: Vec<StreetStats>,
+}
+
+struct ReportObserver {
+    cars: Vec<CarStats>,
+    streets: Vec<StreetStats>,
+    // (street, arrival time) of cars currently waiting at a light
+    waiting_since: Vec<Option<(StreetId, Time)>>,
+}
+
+impl Observer for ReportObserver {
+    fn on_queued(&mut self, time: Time, street_id: StreetId, car_id: CarId, cars_ahead: usize) {
+        let street_stats = &mut self.streets[street_id];
+        street_stats.max_queue_length = street_stats.max_queue_length.max(cars_ahead + 1);
+        self.waiting_since[car_id] = Some((street_id, time));
+    }
+
+    fn on_green(&mut self, time: Time, street_id: StreetId, car_id: CarId) {
+        if let Some((_, arrival_time)) = self.waiting_since[car_id].take() {
+            let waiting_time = time - arrival_time;
+            self.cars[car_id].waiting_time += waiting_time;
+            let street_stats = &mut self.streets[street_id];
+            street_stats.waiting_time += waiting_time;
+            street_stats.cars_passed += 1;
+        }
+    }
+
+    fn on_finished(&mut self, time: Time, car_id: CarId, points: Score) {
+        let car_stats = &mut self.cars[car_id];
+        car_stats.finish_time = Some(time);
+        car_stats.points = points;
+    }
+}
+
+pub(crate) fn simulate_report(input: &PInputData, output: &POutputData) -> SimulationReport {
+    let cars = input.body.car_paths.len();
+    let mut observer = ReportObserver {
+        cars: vec![CarStats::default(); cars],
+        streets: vec![StreetStats::default(); input.body.streets.len()],
+        waiting_since: vec![None; cars],
+    };
+    let score = simulate(input, output, &mut observer);
+    let simulation_duration = input.header.simulation_duration;
+    for (car_id, waiting_since) in observer.waiting_since.iter().enumerate() {
+        if let Some((street_id, arrival_time)) = waiting_since {
+            let waiting_time = simulation_duration.saturating_sub(*arrival_time);
+            observer.cars[car_id].waiting_time += waiting_time;
+            observer.streets[*street_id].waiting_time += waiting_time;
+        }
+    }
+    SimulationReport {
+        score,
+        cars: observer.cars,
+        streets: observer.streets,
+    }
+}
+
+/// Cars waiting at a light, cars finished and score at the end of each bucket of
+/// `bucket_size` seconds, the last bucket ends with the simulation.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Timeline {
+    pub(crate) bucket_size: Time,
+    pub(crate) waiting: Vec<usize>,
+    pub(crate) finished: Vec<usize>,
+    pub(crate) score: Vec<Score>,
+}
+
+struct TimelineObserver {
+    bucket_size: Time,
+    // changes during each bucket
+    waiting: Vec<i64>,
+    finished: Vec<usize>,
+    score: Vec<Score>,
+}
+
+impl TimelineObserver {
+    // bucket i covers times ]i * bucket_size, (i + 1) * bucket_size], time 0 is in bucket 0
+    fn bucket(&self, time: Time) -> usize {
+        (time.saturating_sub(1) / self.bucket_size).min(self.waiting.len() - 1)
+    }
+}
+
+impl Observer for TimelineObserver {
+    fn on_queued(&mut self, time: Time, _street_id: StreetId, _car_id: CarId, _cars_ahead: usize) {
+        let bucket = self.bucket(time);
+        self.waiting[bucket] += 1;
+    }
+
+    fn on_green(&mut self, time: Time, _street_id: StreetId, _car_id: CarId) {
+        let bucket = self.bucket(time);
+        self.waiting[bucket] -= 1;
+    }
+
+    fn on_finished(&mut self, time: Time, _car_id: CarId, points: Score) {
+        let bucket = self.bucket(time);
+        self.finished[bucket] += 1;
+        self.score[bucket] += points;
+    }
+}
+
+pub(crate) fn simulate_timeline(
+    input: &PInputData,
+    output: &POutputData,
+    bucket_size: Time,
+) -> Timeline {
+    assert!(bucket_size > 0, "bucket size must be positive");
+    let simulation_duration = input.header.simulation_duration;
+    let buckets = simulation_duration.div_ceil(bucket_size).max(1);
+    let mut observer = TimelineObserver {
+        bucket_size,
+        waiting: vec![0; buckets],
+        finished: vec![0; buckets],
+        score: vec![0; buckets],
+    };
+    simulate(input, output, &mut observer);
+    let mut waiting: i64 = 0;
+    let mut finished: usize = 0;
+    let mut score: Score = 0;
+    let mut timeline = Timeline {
+        bucket_size,
+        waiting: Vec::with_capacity(buckets),
+        finished: Vec::with_capacity(buckets),
+        score: Vec::with_capacity(buckets),
+    };
+    for bucket in 0..buckets {
+        waiting += observer.waiting[bucket];
+        finished += observer.finished[bucket];
+        score += observer.score[bucket];
+        timeline.waiting.push(waiting as usize);
+        timeline.finished.push(finished);
+        timeline.score.push(score);
+    }
+    timeline
+}
+
+pub(crate) fn format_signed(n: i64) -> String {
+    let formatted = n.abs().to_formatted_string(&Locale::en);
+    if n < 0 {
+        format!("-{}", formatted)
+    } else {
+        format!("+{}", formatted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::test_data;
+    use crate::report::{simulate_report, simulate_timeline, CarStats, StreetStats, Timeline};
+
+    #[test]
+    fn test_simulate_report_example() {
+        let input_data = test_data::get_example_input_data();
+        let output_data = test_data::get_example_output_data();
+        let report = simulate_report(&input_data, &output_data);
+        assert_eq!(report.score, 1002);
+        assert_eq!(
+            report.cars,
+            vec![
+                CarStats {
+                    finish_time: None,
+                    points: 0,
+                    waiting_time: 1,
+                },
+                CarStats {
+                    finish_time: Some(4),
+                    points: 1002,
+                    waiting_time: 0,
+                },
+            ]
+        );
+        // rue-d-amsterdam: car 0 arrives at time 1 but rue-d-athenes is green until time 2
+        assert_eq!(
+            report.streets[1],
+            StreetStats {
+                cars_passed: 1,
+                waiting_time: 1,
+                max_queue_length: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_simulate_timeline_example() {
+        let input_data = test_data::get_example_input_data();
+        let output_data = test_data::get_example_output_data();
+        // car 1 finishes at 4, car 0 waits at rue-d-amsterdam from 1 to 2
+        assert_eq!(
+            simulate_timeline(&input_data, &output_data, 2),
+            Timeline {
+                bucket_size: 2,
+                waiting: vec![0, 0, 0],
+                finished: vec![0, 1, 1],
+                score: vec![0, 1002, 1002],
+            }
+        );
+        let timeline = simulate_timeline(&input_data, &output_data, 1);
+        assert_eq!(timeline.waiting, vec![1, 0, 0, 0, 0, 0]);
+        assert_eq!(timeline.score.last(), Some(&1002));
+    }
+}
@@ -1,193 +1,606 @@
 use crate::data::{PInputData, POutputData};
+use crate::lights::LightSchedules;
+use crate::progress::{CancellationToken, Progress, PROGRESS_INTERVAL};
 use crate::score::Action::{Driving, Waiting};
 use nom::lib::std::collections::VecDeque;
 
 use ahash::AHashMap;
-use fxhash::FxBuildHasher;
-use indexmap::IndexMap;
+use anyhow::bail;
 use log::debug;
-
-type FxIndexMap<K, V> = IndexMap<K, V, FxBuildHasher>;
+use std::str::FromStr;
+use std::sync::Arc;
 
 pub(crate) type Score = usize;
-type StreetId = usize;
-type StreetLength = usize;
-type Time = usize;
-type CarId = usize;
+pub(crate) type StreetId = usize;
+pub(crate) type StreetLength = usize;
+pub(crate) type Time = usize;
+pub(crate) type CarId = usize;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum StartPosition {
+    /// official rule: cars are queued at the light of their first street
+    EndOfFirstStreet,
+    /// cars drive their first street before queuing at its light
+    StartOfFirstStreet,
+}
+
+impl FromStr for StartPosition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "end" => Ok(StartPosition::EndOfFirstStreet),
+            "start" => Ok(StartPosition::StartOfFirstStreet),
+            _ => bail!("unknown start position: {}", s),
+        }
+    }
+}
+
+pub const START_POSITIONS: &[&str] = &["end", "start"];
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Deadline {
+    /// official rule: a car finishing at `simulation_duration` still scores the bonus
+    Inclusive,
+    /// cars must finish strictly before `simulation_duration`
+    Exclusive,
+}
+
+impl FromStr for Deadline {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "inclusive" => Ok(Deadline::Inclusive),
+            "exclusive" => Ok(Deadline::Exclusive),
+            _ => bail!("unknown deadline: {}", s),
+        }
+    }
+}
+
+pub const DEADLINES: &[&str] = &["inclusive", "exclusive"];
+
+/// Scoring rule variants, the default is the official rules.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ScoringRules {
+    /// seconds a car needs to cross an intersection once its light is green
+    pub crossing_delay: Time,
+    pub start_position: StartPosition,
+    pub deadline: Deadline,
+    /// a finished car scores `bonus * bonus_multiplier` plus its seconds to spare
+    pub bonus_multiplier: Score,
+}
 
-#[derive(Debug)]
-enum Action {
+impl ScoringRules {
+    /// Rejects a crossing delay no car could finish with, `simulation_duration` seconds
+    /// being too short to cross a single intersection, and a bonus multiplier whose
+    /// points per car do not fit in a `Score`.
+    pub fn check(&self, simulation_duration: Time, bonus: Score) -> anyhow::Result<()> {
+        if self.crossing_delay >= simulation_duration {
+            bail!(
+                "crossing delay of {}s, the simulation only lasts {}s",
+                self.crossing_delay,
+                simulation_duration
+            );
+        }
+        if bonus
+            .checked_mul(self.bonus_multiplier)
+            .and_then(|bonus| bonus.checked_add(simulation_duration))
+            .is_none()
+        {
+            bail!(
+                "bonus multiplier of {} too large for a bonus of {}",
+                self.bonus_multiplier,
+                bonus
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Default for ScoringRules {
+    fn default() -> Self {
+        ScoringRules {
+            crossing_delay: 0,
+            start_position: StartPosition::EndOfFirstStreet,
+            deadline: Deadline::Inclusive,
+            bonus_multiplier: 1,
+        }
+    }
+}
+
+/// Simulation events, times are in seconds as in the problem statement.
+///
+/// All methods default to no-op so that `compute_score` pays nothing for them.
+pub(crate) trait Observer {
+    /// `car_id` reached the light at the end of `street_id` behind `cars_ahead` cars.
+    fn on_queued(&mut self, _time: Time, _street_id: StreetId, _car_id: CarId, _cars_ahead: usize) {
+    }
+    /// `car_id` crossed the intersection at the end of `street_id`.
+    fn on_green(&mut self, _time: Time, _street_id: StreetId, _car_id: CarId) {}
+    /// `car_id` reached the end of its path.
+    fn on_finished(&mut self, _time: Time, _car_id: CarId, _points: Score) {}
+}
+
+impl Observer for () {}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum Action {
     Waiting(StreetId),
     Driving(StreetId, StreetLength),
     Finished(Time),
 }
 
-struct CarTracker {
-    id: CarId,
-    actions: VecDeque<Action>,
-    distance_current_street: StreetLength,
+/// Current action of a car, its next ones are read from its path in the simulator.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) struct CarTracker {
+    pub(crate) id: CarId,
+    // index in the car path of the street the car drives or waits at the end of
+    pub(crate) street_index: usize,
+    pub(crate) action: Action,
+    pub(crate) distance_current_street: StreetLength,
 }
 
-// offset, duration, period
-type LightSchedule = (usize, usize, usize);
+/// Everything needed to resume a simulation, cf `checkpoint`.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct SimulationState {
+    // next second to simulate
+    pub(crate) time: Time,
+    pub(crate) score: Score,
+    pub(crate) finished_cars: usize,
+    pub(crate) stranded_cars: usize,
+    pub(crate) car_trackers: Vec<CarTracker>,
+    // queue of each street, indexes in car_trackers which may skip some car ids
+    pub(crate) street_queues: Vec<VecDeque<usize>>,
+    // streets with a non empty queue, the only ones lights are checked for
+    pub(crate) active_streets: Vec<StreetId>,
+}
 
-fn is_green(time: Time, light_schedule: &LightSchedule) -> bool {
-    let &(offset, duration, period) = light_schedule;
-    let tmod = time % period;
-    offset <= tmod && tmod < offset + duration
+/// Queues `tracker_index` at the light of `street_id`, returns the number of cars ahead.
+fn push_queue(
+    street_queues: &mut [VecDeque<usize>],
+    active_streets: &mut Vec<StreetId>,
+    street_id: StreetId,
+    tracker_index: usize,
+) -> usize {
+    let street_queue = &mut street_queues[street_id];
+    if street_queue.is_empty() {
+        active_streets.push(street_id);
+    }
+    street_queue.push_back(tracker_index);
+    street_queue.len() - 1
 }
 
-fn build_light_schedule(
-    output: &POutputData,
-    street_name_id_length: &AHashMap<String, (StreetId, StreetLength)>,
-) -> AHashMap<StreetId, LightSchedule> {
-    let light_schedules = output
-        .intersection_schedules
-        .iter()
-        .map(|intersection_schedule| {
-            let period: usize = intersection_schedule
-                .light_schedules
-                .iter()
-                .map(|(_, duration)| duration)
-                .sum();
-            let mut offset: usize = 0;
-            let intersection_light_schedule = intersection_schedule.light_schedules.iter().map(
-                |(street_name, light_duration)| {
-                    offset += light_duration;
-                    let (street_id, _) = street_name_id_length
-                        .get(street_name)
-                        .unwrap_or_else(|| panic!("unknown street name: {}", street_name));
-                    (
-                        *street_id,
-                        (offset - *light_duration, *light_duration, period),
-                    )
-                },
-            );
-            intersection_light_schedule.collect::<Vec<(StreetId, LightSchedule)>>()
-        })
-        .flatten()
-        .collect::<AHashMap<StreetId, LightSchedule>>();
-    light_schedules
+const CROSSING_DELAY_TOO_LONG: &str = "crossing delay too long, cf ScoringRules::check";
+
+/// Input data prepared once to simulate many outputs.
+pub(crate) struct Simulator {
+    simulation_duration: Time,
+    bonus: Score,
+    street_name_id_length: AHashMap<String, (StreetId, StreetLength)>,
+    street_lengths: Vec<StreetLength>,
+    max_street_length: StreetLength,
+    rules: ScoringRules,
+    // streets of all car paths end to end,
+    // car i drives path_streets[path_starts[i]..path_starts[i + 1]]
+    path_streets: Vec<StreetId>,
+    path_starts: Vec<usize>,
+    simulated_cars: Vec<CarId>,
+    progress: Option<Arc<dyn Progress>>,
+    cancellation: Option<CancellationToken>,
 }
 
-pub fn compute_score(input: &PInputData, output: &POutputData) -> Score {
-    let street_name_id_length = input
-        .body
-        .streets
-        .iter()
-        .enumerate()
-        .map(|(street_id, street)| {
-            (
-                street.street_name.clone(),
-                (street_id, street.street_length),
-            )
-        })
-        .collect::<AHashMap<String, (StreetId, StreetLength)>>();
-
-    let light_schedule_of_street: AHashMap<StreetId, LightSchedule> =
-        build_light_schedule(output, &street_name_id_length);
-
-    let mut car_trackers: Vec<CarTracker> = vec![];
-    let mut street_queues: FxIndexMap<StreetId, VecDeque<CarId>> = FxIndexMap::default();
-    for (car_id, car_path) in input.body.car_paths.iter().enumerate() {
-        let mut actions: VecDeque<Action> = VecDeque::new();
-        let streets = car_path.streets;
-        for (i, street_name) in car_path.street_names.iter().enumerate() {
-            let (street_id, street_length) = street_name_id_length
-                .get(street_name)
-                .expect("unknown street name");
-            if i == 0 {
-                street_queues
-                    .entry(*street_id)
-                    .or_insert_with(VecDeque::new)
-                    .push_back(car_id);
+impl Simulator {
+    pub(crate) fn new(input: &PInputData) -> Self {
+        let street_name_id_length = input
+            .body
+            .streets
+            .iter()
+            .enumerate()
+            .map(|(street_id, street)| {
+                (
+                    street.street_name.clone(),
+                    (street_id, street.street_length),
+                )
+            })
+            .collect::<AHashMap<String, (StreetId, StreetLength)>>();
+
+        let mut path_streets: Vec<StreetId> = vec![];
+        let mut path_starts: Vec<usize> = vec![0];
+        for car_path in &input.body.car_paths {
+            for street_name in &car_path.street_names {
+                let (street_id, _) = street_name_id_length
+                    .get(street_name)
+                    .expect("unknown street name");
+                path_streets.push(*street_id);
+            }
+            path_starts.push(path_streets.len());
+        }
+
+        let street_lengths: Vec<StreetLength> = input
+            .body
+            .streets
+            .iter()
+            .map(|street| street.street_length)
+            .collect();
+        let max_street_length = street_lengths.iter().copied().max().unwrap_or(0);
+        Simulator {
+            simulation_duration: input.header.simulation_duration,
+            bonus: input.header.bonus,
+            street_name_id_length,
+            street_lengths,
+            max_street_length,
+            rules: ScoringRules::default(),
+            path_streets,
+            path_starts,
+            simulated_cars: (0..input.body.car_paths.len()).collect(),
+            progress: None,
+            cancellation: None,
+        }
+    }
+
+    pub(crate) fn with_rules(mut self, rules: ScoringRules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// Simulates for `simulation_duration` seconds instead of the input file duration.
+    pub(crate) fn with_simulation_duration(mut self, simulation_duration: Time) -> Self {
+        self.simulation_duration = simulation_duration;
+        self
+    }
+
+    /// Notifies `progress` every `PROGRESS_INTERVAL` simulated seconds.
+    pub(crate) fn with_progress(mut self, progress: Arc<dyn Progress>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Stops simulations early once `cancellation` is cancelled, checked every
+    /// `PROGRESS_INTERVAL` simulated seconds: scores are then partial.
+    pub(crate) fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// Stops simulating cars that cannot finish whatever the schedule.
+    ///
+    /// Skipped cars no longer block queues, so scores may be higher than official ones:
+    /// use it to screen candidates quickly, not to compute submission scores.
+    pub(crate) fn skip_unreachable(mut self, unreachable_cars: &[CarId]) -> Self {
+        let before = self.simulated_cars.len();
+        self.simulated_cars
+            .retain(|car_id| unreachable_cars.binary_search(car_id).is_err());
+        debug!(
+            "skipping {} unreachable cars",
+            before - self.simulated_cars.len()
+        );
+        self
+    }
+
+    fn path(&self, car_id: CarId) -> &[StreetId] {
+        &self.path_streets[self.path_starts[car_id]..self.path_starts[car_id + 1]]
+    }
+
+    pub(crate) fn score(&self, output: &POutputData) -> Score {
+        self.simulate(output, &mut ())
+    }
+
+    pub(crate) fn simulate<O: Observer>(&self, output: &POutputData, observer: &mut O) -> Score {
+        self.simulate_without(output, None, observer)
+    }
+
+    /// Score as if `car_id` was not in the input file.
+    pub(crate) fn score_without_car(&self, output: &POutputData, car_id: CarId) -> Score {
+        self.simulate_without(output, Some(car_id), &mut ())
+    }
+
+    fn simulate_without<O: Observer>(
+        &self,
+        output: &POutputData,
+        removed_car: Option<CarId>,
+        observer: &mut O,
+    ) -> Score {
+        let light_schedules = self.light_schedules(output);
+        let mut state = self.start(&light_schedules, removed_car, observer);
+        // simulated in chunks only when someone follows or may cancel the simulation
+        let chunked = self.progress.is_some() || self.cancellation.is_some();
+        loop {
+            let until = if chunked {
+                state.time.saturating_add(PROGRESS_INTERVAL)
             } else {
-                // car start at the end of first street
-                actions.push_back(Driving(*street_id, *street_length))
+                self.simulation_duration
+            };
+            self.run(&light_schedules, &mut state, until, observer);
+            let over = state.time >= self.simulation_duration
+                || state.finished_cars + state.stranded_cars == state.car_trackers.len();
+            if let Some(progress) = &self.progress {
+                // nothing happens any more once every car finished or is stranded
+                let time = if over {
+                    self.simulation_duration
+                } else {
+                    state.time
+                };
+                progress.on_progress(time, state.finished_cars);
             }
-            if i != (streets - 1) {
-                // wait at the end of all streets except last one
-                actions.push_back(Waiting(*street_id));
+            let cancelled = self
+                .cancellation
+                .as_ref()
+                .is_some_and(|cancellation| cancellation.is_cancelled());
+            if over || cancelled {
+                return state.score;
             }
         }
-        debug!("car {}: actions: {:?}", car_id, actions);
-        car_trackers.push(CarTracker {
-            id: car_id,
-            actions,
-            distance_current_street: 0,
-        })
-    }
-
-    let mut score: Score = 0;
-    for time in 0..input.header.simulation_duration {
-        // move at most one car out of intersection if light is green
-        for (street_id, street_queue) in street_queues.iter_mut() {
-            let light_schedule = light_schedule_of_street.get(street_id);
-            if let Some(light_schedule) = light_schedule {
-                if is_green(time, light_schedule) {
-                    let car_id_out = street_queue.pop_front();
-                    if let Some(car_id_out) = car_id_out {
-                        let car_tracker =
-                            car_trackers.get_mut(car_id_out).expect("car should exist");
-                        debug!(
-                            "car {} got green light at the end of street {} at time {}",
-                            car_id_out, street_id, time
-                        );
-                        // There is no delay while a car passes through an intersection
-                        // it means this car will move by one on its next street right away
-                        car_tracker.distance_current_street = 1;
-                        car_tracker.actions.pop_front(); // car no longer waiting
+    }
+
+    /// Light schedule of each street of the input file.
+    pub(crate) fn light_schedules(&self, output: &POutputData) -> LightSchedules {
+        LightSchedules::new(output, &self.street_name_id_length)
+    }
+
+    /// State at time 0, cars are queued at the end of their first street.
+    pub(crate) fn initial_state<O: Observer>(
+        &self,
+        output: &POutputData,
+        observer: &mut O,
+    ) -> SimulationState {
+        let light_schedules = self.light_schedules(output);
+        self.start(&light_schedules, None, observer)
+    }
+
+    /// Simulates from `state` up to time `until` (excluded) or the end of the simulation.
+    pub(crate) fn resume<O: Observer>(
+        &self,
+        output: &POutputData,
+        state: &mut SimulationState,
+        until: Time,
+        observer: &mut O,
+    ) {
+        let light_schedules = self.light_schedules(output);
+        self.run(&light_schedules, state, until, observer);
+    }
+
+    fn start<O: Observer>(
+        &self,
+        light_schedules: &LightSchedules,
+        removed_car: Option<CarId>,
+        observer: &mut O,
+    ) -> SimulationState {
+        let mut state = SimulationState {
+            time: 0,
+            score: 0,
+            finished_cars: 0,
+            stranded_cars: 0,
+            car_trackers: vec![],
+            street_queues: vec![VecDeque::new(); self.street_lengths.len()],
+            active_streets: vec![],
+        };
+        for (tracker_index, &car_id) in self
+            .simulated_cars
+            .iter()
+            .filter(|&&car_id| Some(car_id) != removed_car)
+            .enumerate()
+        {
+            let street_id = self.path(car_id)[0];
+            let car_tracker = match self.rules.start_position {
+                StartPosition::EndOfFirstStreet => {
+                    if light_schedules.never_green(street_id) {
+                        state.stranded_cars += 1;
+                    }
+                    let cars_ahead = push_queue(
+                        &mut state.street_queues,
+                        &mut state.active_streets,
+                        street_id,
+                        tracker_index,
+                    );
+                    observer.on_queued(0, street_id, car_id, cars_ahead);
+                    CarTracker {
+                        id: car_id,
+                        street_index: 0,
+                        action: Waiting(street_id),
+                        distance_current_street: 0,
                     }
                 }
+                // as if the car had just crossed the previous intersection, without delay
+                StartPosition::StartOfFirstStreet => CarTracker {
+                    id: car_id,
+                    street_index: 0,
+                    action: Driving(street_id, self.street_lengths[street_id]),
+                    distance_current_street: self
+                        .rules
+                        .crossing_delay
+                        .checked_add(1)
+                        .expect(CROSSING_DELAY_TOO_LONG),
+                },
+            };
+            state.car_trackers.push(car_tracker);
+        }
+        state
+    }
+
+    fn run<O: Observer>(
+        &self,
+        light_schedules: &LightSchedules,
+        state: &mut SimulationState,
+        until: Time,
+        observer: &mut O,
+    ) {
+        let crossing_delay = self.rules.crossing_delay;
+        let bonus = self.bonus * self.rules.bonus_multiplier;
+        // a car finishing at time_matlab scores if time_matlab < deadline
+        let deadline = match self.rules.deadline {
+            Deadline::Inclusive => self.simulation_duration + 1,
+            Deadline::Exclusive => self.simulation_duration,
+        };
+        let SimulationState {
+            time,
+            score,
+            finished_cars,
+            stranded_cars,
+            car_trackers,
+            street_queues,
+            active_streets,
+        } = state;
+        // cars driving on a street by the second they reach its end, in a ring since no
+        // street takes longer than the longest one to drive
+        let ring_len = self
+            .max_street_length
+            .checked_add(crossing_delay)
+            .and_then(|ring_len| ring_len.checked_add(1))
+            .expect(CROSSING_DELAY_TOO_LONG);
+        let mut arrivals: Vec<Vec<usize>> = vec![vec![]; ring_len];
+        for (tracker_index, car_tracker) in car_trackers.iter().enumerate() {
+            if let Driving(_, street_length) = car_tracker.action {
+                let remaining = (street_length + crossing_delay)
+                    .saturating_sub(car_tracker.distance_current_street);
+                arrivals[(*time + remaining) % ring_len].push(tracker_index);
             }
         }
 
-        // remove empty queues
-        street_queues.retain(|_street_id, street_queue| !street_queue.is_empty());
-
-        // set cars at the end of their street to waiting or finished
-        // move cars still in transit
-        for car_tracker in car_trackers.iter_mut() {
-            if let Some(Driving(_, street_length)) = car_tracker.actions.front() {
-                if car_tracker.distance_current_street >= *street_length {
-                    // reset for next street
-                    car_tracker.distance_current_street = 0;
-                    car_tracker.actions.pop_front(); // discard driving action
-                    match car_tracker.actions.front() {
-                        // retrieve next action
-                        Some(Waiting(street_id)) => {
-                            // queue up
-                            street_queues
-                                .entry(*street_id)
-                                .or_insert_with(VecDeque::new)
-                                .push_back(car_tracker.id);
-                        }
-                        None => {
-                            // empty actions, car is finished
-                            debug!("car {} finished with time {}", car_tracker.id, time);
-                            car_tracker.actions.push_back(Action::Finished(time));
-                            let time_matlab = time + 1;
-                            if time_matlab <= input.header.simulation_duration {
-                                score += input.header.bonus
-                                    + (input.header.simulation_duration - time_matlab)
-                            }
-                        }
-                        _ => unreachable!(),
+        // next second each active street is green, computed once instead of every second
+        let next_green = |street_id: StreetId, time: Time| {
+            light_schedules
+                .next_green(street_id, time)
+                .unwrap_or(Time::MAX)
+        };
+        let mut green_times: Vec<Time> = active_streets
+            .iter()
+            .map(|&street_id| next_green(street_id, *time))
+            .collect();
+
+        // no more points once every car is either finished or stranded on a never green street
+        while *time < until.min(self.simulation_duration)
+            && *finished_cars + *stranded_cars < car_trackers.len()
+        {
+            // move at most one car out of intersection if light is green
+            for (&street_id, green_time) in active_streets.iter().zip(green_times.iter_mut()) {
+                if *green_time == *time {
+                    let tracker_index_out = street_queues[street_id]
+                        .pop_front()
+                        .expect("active queues are not empty");
+                    let car_tracker = car_trackers
+                        .get_mut(tracker_index_out)
+                        .expect("car should exist");
+                    observer.on_green(*time, street_id, car_tracker.id);
+                    // There is no delay while a car passes through an intersection
+                    // it means this car will move by one on its next street right away
+                    // a crossing delay is driven as extra distance on the next street
+                    // cars never wait at the end of the last street of their path
+                    car_tracker.street_index += 1;
+                    let next_street_id = self.path(car_tracker.id)[car_tracker.street_index];
+                    let street_length = self.street_lengths[next_street_id];
+                    car_tracker.action = Driving(next_street_id, street_length);
+                    let remaining = street_length + crossing_delay - 1;
+                    arrivals[(*time + remaining) % ring_len].push(tracker_index_out);
+                    *green_time = next_green(street_id, *time + 1);
+                }
+            }
+
+            // forget empty queues
+            let mut kept = 0;
+            for i in 0..active_streets.len() {
+                if !street_queues[active_streets[i]].is_empty() {
+                    active_streets[kept] = active_streets[i];
+                    green_times[kept] = green_times[i];
+                    kept += 1;
+                }
+            }
+            active_streets.truncate(kept);
+            green_times.truncate(kept);
+
+            // set cars at the end of their street to waiting or finished
+            // cars queue up in car order when they reach the same light at the same time
+            let mut arrived_cars = std::mem::take(&mut arrivals[*time % ring_len]);
+            arrived_cars.sort_unstable();
+            for &tracker_index in &arrived_cars {
+                let car_tracker = &mut car_trackers[tracker_index];
+                // reset for next street
+                car_tracker.distance_current_street = 0;
+                let street_id = match car_tracker.action {
+                    Driving(street_id, _) => street_id,
+                    _ => unreachable!("arriving cars drive"),
+                };
+                if car_tracker.street_index + 1 < self.path(car_tracker.id).len() {
+                    // queue up, the car is at the light on the next second
+                    car_tracker.action = Waiting(street_id);
+                    if light_schedules.never_green(street_id) {
+                        *stranded_cars += 1;
+                    }
+                    let cars_ahead =
+                        push_queue(street_queues, active_streets, street_id, tracker_index);
+                    if cars_ahead == 0 {
+                        green_times.push(next_green(street_id, *time + 1));
                     }
+                    observer.on_queued(*time + 1, street_id, car_tracker.id, cars_ahead);
                 } else {
-                    // move car
-                    car_tracker.distance_current_street += 1;
+                    // end of the path, car is finished
+                    car_tracker.action = Action::Finished(*time);
+                    *finished_cars += 1;
+                    let time_matlab = *time + 1;
+                    if time_matlab < deadline {
+                        let points = bonus + (self.simulation_duration - time_matlab);
+                        observer.on_finished(time_matlab, car_tracker.id, points);
+                        *score += points
+                    }
+                }
+            }
+            arrived_cars.clear();
+            arrivals[*time % ring_len] = arrived_cars;
+
+            *time += 1;
+            if *finished_cars + *stranded_cars == car_trackers.len() {
+                debug!(
+                    "simulation stopped at time {}: {} finished cars, {} stranded cars, {} steps saved",
+                    *time - 1,
+                    finished_cars,
+                    stranded_cars,
+                    self.simulation_duration - *time
+                );
+            }
+        }
+
+        // distances as if cars had moved by one every second
+        for remaining in 0..ring_len {
+            for &tracker_index in &arrivals[(*time + remaining) % ring_len] {
+                let car_tracker = &mut car_trackers[tracker_index];
+                if let Driving(_, street_length) = car_tracker.action {
+                    car_tracker.distance_current_street =
+                        street_length + crossing_delay - remaining;
                 }
             }
         }
     }
-    score
+}
+
+pub fn compute_score(input: &PInputData, output: &POutputData) -> Score {
+    Simulator::new(input).score(output)
+}
+
+pub(crate) fn simulate<O: Observer>(
+    input: &PInputData,
+    output: &POutputData,
+    observer: &mut O,
+) -> Score {
+    Simulator::new(input).simulate(output, observer)
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::analysis::unreachable_cars;
     use crate::data::test_data;
-    use crate::score::compute_score;
+    use crate::data::{PInputData, POutputData};
+    use crate::parser::{parse_input_file, parse_output_file};
+    use crate::score::{
+        compute_score, Deadline, Score, ScoringRules, Simulator, StartPosition, Time,
+    };
+
+    fn compute_score_with_rules(
+        input: &PInputData,
+        output: &POutputData,
+        rules: ScoringRules,
+    ) -> Score {
+        Simulator::new(input).with_rules(rules).score(output)
+    }
 
     #[test]
     fn test_compute_score_example() {
@@ -196,4 +609,211 @@ mod tests {
         let score = compute_score(&input_data, &output_data);
         assert_eq!(score, 1002);
     }
+
+    #[test]
+    fn test_skip_unreachable() {
+        let mut input_data = test_data::get_example_input_data();
+        input_data.header.simulation_duration = 5;
+        let output_data = test_data::get_example_output_data();
+        let simulator =
+            Simulator::new(&input_data).skip_unreachable(&unreachable_cars(&input_data));
+        // car 0 cannot finish and never blocks car 1
+        assert_eq!(simulator.score(&output_data), 1001);
+        assert_eq!(compute_score(&input_data, &output_data), 1001);
+    }
+
+    #[test]
+    fn test_early_termination() {
+        let mut input_data = test_data::get_example_input_data();
+        // would take a while if every step was simulated
+        input_data.header.simulation_duration = 1_000_000_000_000;
+        let mut output_data = test_data::get_example_output_data();
+        // car 0 finishes at 7, car 1 at 4
+        assert_eq!(
+            compute_score(&input_data, &output_data),
+            2 * 1000 + 2 * 1_000_000_000_000 - 7 - 4
+        );
+        // car 0 and car 1 are stranded on rue-de-moscou
+        output_data.intersection_schedules.pop();
+        output_data.schedules = 2;
+        assert_eq!(compute_score(&input_data, &output_data), 0);
+    }
+
+    #[test]
+    fn test_default_rules_are_official() {
+        let rules = ScoringRules::default();
+        // scores of out/*.out given by the official judge system
+        for (name, official_score) in [
+            ("a", 2_002),
+            ("b", 4_566_783),
+            ("c", 1_299_593),
+            ("d", 1_586_428),
+            ("e", 710_095),
+            ("f", 1_408_553),
+        ] {
+            let input_data = parse_input_file(&format!("res/{}.txt", name)).unwrap();
+            let output_data = parse_output_file(&format!("out/{}.out", name)).unwrap();
+            assert_eq!(
+                compute_score_with_rules(&input_data, &output_data, rules),
+                official_score,
+                "{}",
+                name
+            );
+        }
+        let mut input_data = test_data::get_example_input_data();
+        let mut output_data = test_data::get_example_output_data();
+        // car 1 finishes right at the end of the simulation
+        input_data.header.simulation_duration = 4;
+        assert_eq!(
+            compute_score_with_rules(&input_data, &output_data, rules),
+            1000
+        );
+        output_data.intersection_schedules.pop();
+        output_data.schedules = 2;
+        assert_eq!(
+            compute_score_with_rules(&input_data, &output_data, rules),
+            0
+        );
+    }
+
+    #[test]
+    fn test_check_rules() {
+        let rules = ScoringRules {
+            crossing_delay: 5,
+            ..ScoringRules::default()
+        };
+        assert!(rules.check(6, 1000).is_ok());
+        assert!(rules.check(5, 1000).is_err());
+        let rules = ScoringRules {
+            crossing_delay: Time::MAX,
+            ..rules
+        };
+        assert!(rules.check(6, 1000).is_err());
+    }
+
+    #[test]
+    fn test_check_bonus_multiplier() {
+        let rules = ScoringRules {
+            bonus_multiplier: Score::MAX / 1000,
+            ..ScoringRules::default()
+        };
+        assert!(rules.check(6, 1000).is_ok());
+        assert!(rules.check(6, 1001).is_err());
+        let rules = ScoringRules {
+            bonus_multiplier: Score::MAX,
+            ..rules
+        };
+        // the seconds to spare overflow
+        assert!(rules.check(6, 1).is_err());
+        assert!(rules.check(6, 0).is_ok());
+    }
+
+    #[test]
+    fn test_large_crossing_delay() {
+        let input_data = test_data::get_example_input_data();
+        let output_data = test_data::get_example_output_data();
+        let rules = ScoringRules {
+            crossing_delay: 100_000_000_000,
+            ..ScoringRules::default()
+        };
+        assert!(rules
+            .check(
+                input_data.header.simulation_duration,
+                input_data.header.bonus
+            )
+            .is_err());
+        // the longest delay allowed: no car crosses an intersection in time
+        for start_position in [
+            StartPosition::EndOfFirstStreet,
+            StartPosition::StartOfFirstStreet,
+        ] {
+            let rules = ScoringRules {
+                crossing_delay: 5,
+                start_position,
+                ..rules
+            };
+            assert!(rules
+                .check(
+                    input_data.header.simulation_duration,
+                    input_data.header.bonus
+                )
+                .is_ok());
+            assert_eq!(
+                compute_score_with_rules(&input_data, &output_data, rules),
+                0
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "crossing delay too long")]
+    fn test_crossing_delay_overflow() {
+        let input_data = test_data::get_example_input_data();
+        let output_data = test_data::get_example_output_data();
+        let rules = ScoringRules {
+            crossing_delay: Time::MAX,
+            ..ScoringRules::default()
+        };
+        compute_score_with_rules(&input_data, &output_data, rules);
+    }
+
+    #[test]
+    fn test_resume_every_second() {
+        let mut input_data = test_data::get_example_input_data();
+        input_data.header.simulation_duration = 12;
+        let output_data = test_data::get_example_output_data();
+        let official = ScoringRules::default();
+        for rules in [
+            official,
+            ScoringRules {
+                crossing_delay: 2,
+                ..official
+            },
+            ScoringRules {
+                start_position: StartPosition::StartOfFirstStreet,
+                ..official
+            },
+        ] {
+            let simulator = Simulator::new(&input_data).with_rules(rules);
+            let mut state = simulator.initial_state(&output_data, &mut ());
+            for until in 1..=12 {
+                simulator.resume(&output_data, &mut state, until, &mut ());
+            }
+            assert_eq!(state.score, simulator.score(&output_data), "{:?}", rules);
+        }
+    }
+
+    #[test]
+    fn test_rule_variants() {
+        let mut input_data = test_data::get_example_input_data();
+        let output_data = test_data::get_example_output_data();
+        let official = ScoringRules::default();
+        let score = |input_data, rules| compute_score_with_rules(input_data, &output_data, rules);
+        // car 1 crosses two intersections and finishes at 6 instead of 4
+        let delayed = ScoringRules {
+            crossing_delay: 1,
+            ..official
+        };
+        assert_eq!(score(&input_data, delayed), 1000);
+        // car 1 drives rue-d-athenes first and finishes at 5
+        let from_start = ScoringRules {
+            start_position: StartPosition::StartOfFirstStreet,
+            ..official
+        };
+        assert_eq!(score(&input_data, from_start), 1001);
+        let doubled = ScoringRules {
+            bonus_multiplier: 2,
+            ..official
+        };
+        assert_eq!(score(&input_data, doubled), 2002);
+        input_data.header.simulation_duration = 4;
+        let exclusive = ScoringRules {
+            deadline: Deadline::Exclusive,
+            ..official
+        };
+        assert_eq!(
+            compute_score_with_rules(&input_data, &output_data, exclusive),
+            0
+        );
+    }
 }
@@ -0,0 +1,196 @@
+use crate::data::{PInputData, PIntersectionSchedule, POutputData};
+use crate::generator::street_traffic;
+use crate::parallel::parallel_map;
+use crate::report::format_signed;
+use crate::score::{Simulator, StreetId};
+use ahash::AHashMap;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum GreenChange {
+    /// one more second of green
+    More,
+    /// one less second of green, for streets green for more than 1 second
+    Less,
+    /// street missing from its intersection schedule, added with 1 second of green
+    Added,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct StreetGain {
+    pub street_id: StreetId,
+    pub change: GreenChange,
+    pub score_delta: i64,
+}
+
+// where the change is applied in the output file
+#[derive(Debug)]
+enum Position {
+    Light {
+        schedule_index: usize,
+        light_index: usize,
+    },
+    AppendedTo(usize),
+    NewSchedule,
+}
+
+struct Variant {
+    street_id: StreetId,
+    change: GreenChange,
+    position: Position,
+}
+
+fn variants(input: &PInputData, output: &POutputData) -> Vec<Variant> {
+    let street_id_of_name = input.street_id_of_name();
+    let mut variants = vec![];
+    let mut scheduled = vec![false; input.body.streets.len()];
+    let mut schedule_index_of_intersection: AHashMap<usize, usize> = AHashMap::default();
+    for (schedule_index, intersection_schedule) in output.intersection_schedules.iter().enumerate()
+    {
+        schedule_index_of_intersection
+            .insert(intersection_schedule.intersection_id, schedule_index);
+        for (light_index, (street_name, duration)) in
+            intersection_schedule.light_schedules.iter().enumerate()
+        {
+            let street_id = street_id_of_name[street_name.as_str()];
+            scheduled[street_id] = true;
+            let mut changes = vec![GreenChange::More];
+            // 0 seconds would remove the street from the schedule
+            if *duration > 1 {
+                changes.push(GreenChange::Less);
+            }
+            for change in changes {
+                variants.push(Variant {
+                    street_id,
+                    change,
+                    position: Position::Light {
+                        schedule_index,
+                        light_index,
+                    },
+                });
+            }
+        }
+    }
+    let traffic = street_traffic(input);
+    for (street_id, street) in input.body.streets.iter().enumerate() {
+        if scheduled[street_id] || traffic[street_id].cars == 0 {
+            continue;
+        }
+        variants.push(Variant {
+            street_id,
+            change: GreenChange::Added,
+            position: match schedule_index_of_intersection.get(&street.intersection_end) {
+                Some(&schedule_index) => Position::AppendedTo(schedule_index),
+                None => Position::NewSchedule,
+            },
+        });
+    }
+    variants
+}
+
+fn apply(input: &PInputData, output: &POutputData, variant: &Variant) -> POutputData {
+    let mut output = output.clone();
+    let street = &input.body.streets[variant.street_id];
+    match variant.position {
+        Position::Light {
+            schedule_index,
+            light_index,
+        } => {
+            let duration =
+                &mut output.intersection_schedules[schedule_index].light_schedules[light_index].1;
+            match variant.change {
+                GreenChange::Less => *duration -= 1,
+                _ => *duration += 1,
+            }
+        }
+        Position::AppendedTo(schedule_index) => {
+            let intersection_schedule = &mut output.intersection_schedules[schedule_index];
+            intersection_schedule
+                .light_schedules
+                .push((street.street_name.clone(), 1));
+            intersection_schedule.incoming_streets += 1;
+        }
+        Position::NewSchedule => {
+            output.intersection_schedules.push(PIntersectionSchedule {
+                intersection_id: street.intersection_end,
+                incoming_streets: 1,
+                light_schedules: vec![(street.street_name.clone(), 1)],
+            });
+            output.schedules += 1;
+        }
+    }
+    output
+}
+
+/// Score change of one more and one less second of green for each scheduled street
+/// (one less only when green for more than 1 second), and of adding each unscheduled street cars wait on with 1 second of green.
+///
+/// Every change is simulated exactly, on `threads` threads. The list is ranked by score
+/// change, best first.
+pub fn street_gains(input: &PInputData, output: &POutputData, threads: usize) -> Vec<StreetGain> {
+    let simulator = Simulator::new(input);
+    let score = simulator.score(output) as i64;
+    let variants = variants(input, output);
+    let scores = parallel_map(&variants, threads, |variant| {
+        simulator.score(&apply(input, output, variant))
+    });
+    let mut gains: Vec<StreetGain> = variants
+        .iter()
+        .zip(scores)
+        .map(|(variant, variant_score)| StreetGain {
+            street_id: variant.street_id,
+            change: variant.change,
+            score_delta: variant_score as i64 - score,
+        })
+        .collect();
+    gains.sort_by_key(|gain| (std::cmp::Reverse(gain.score_delta), gain.street_id));
+    gains
+}
+
+pub fn print_gains(input: &PInputData, gains: &[StreetGain], limit: Option<usize>) {
+    for gain in gains.iter().take(limit.unwrap_or(usize::MAX)) {
+        let street = &input.body.streets[gain.street_id];
+        let change = match gain.change {
+            GreenChange::More => "+1s",
+            GreenChange::Less => "-1s",
+            GreenChange::Added => "added 1s",
+        };
+        println!(
+            "{} (intersection {}) {}: {}",
+            street.street_name,
+            street.intersection_end,
+            change,
+            format_signed(gain.score_delta)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::test_data;
+    use crate::sensitivity::{street_gains, GreenChange, StreetGain};
+
+    #[test]
+    fn test_street_gains_example() {
+        let input_data = test_data::get_example_input_data();
+        let mut output_data = test_data::get_example_output_data();
+        // rue-de-moscou is no longer scheduled
+        output_data.intersection_schedules.pop();
+        output_data.schedules = 2;
+        let gains = street_gains(&input_data, &output_data, 2);
+        assert_eq!(
+            gains[0],
+            StreetGain {
+                street_id: 4,
+                change: GreenChange::Added,
+                score_delta: 1002,
+            }
+        );
+        // 2 changes for each of the 3 scheduled lights but rue-d-amsterdam, green for
+        // 1 second, rue-de-moscou added
+        assert_eq!(gains.len(), 6);
+        assert!(!gains
+            .iter()
+            .any(|gain| gain.street_id == 1 && gain.change == GreenChange::Less));
+        assert!(gains[1..].iter().all(|gain| gain.score_delta == 0));
+    }
+}
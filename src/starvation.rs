@@ -0,0 +1,140 @@
+use crate::analysis::min_travel_times;
+use crate::data::{PInputData, POutputData};
+use crate::lights::LightSchedules;
+use crate::score::{CarId, Observer, Score, Simulator, StreetId, Time};
+use ahash::AHashMap;
+use num_format::{Locale, ToFormattedString};
+
+#[derive(Debug, PartialEq)]
+pub struct StarvedStreet {
+    pub street_id: StreetId,
+    // cars waiting forever at the end of the street
+    pub cars: Vec<CarId>,
+    // best score these cars could have earned, cf `min_travel_times`
+    pub lost_upper_bound: Score,
+}
+
+struct StarvationObserver {
+    light_schedules: LightSchedules,
+    stranded_cars: AHashMap<StreetId, Vec<CarId>>,
+}
+
+impl Observer for StarvationObserver {
+    fn on_queued(&mut self, _time: Time, street_id: StreetId, car_id: CarId, _cars_ahead: usize) {
+        if self.light_schedules.never_green(street_id) {
+            self.stranded_cars
+                .entry(street_id)
+                .or_default()
+                .push(car_id);
+        }
+    }
+}
+
+/// Scores `output` and lists streets cars queue on but that are never green.
+///
+/// A street is never green when it is missing from its intersection schedule or
+/// scheduled for 0 seconds.
+pub(crate) fn score_with_starvation(
+    simulator: &Simulator,
+    input: &PInputData,
+    output: &POutputData,
+) -> (Score, Vec<StarvedStreet>) {
+    let mut observer = StarvationObserver {
+        light_schedules: simulator.light_schedules(output),
+        stranded_cars: AHashMap::default(),
+    };
+    let score = simulator.simulate(output, &mut observer);
+
+    let simulation_duration = input.header.simulation_duration;
+    let min_travel_times = min_travel_times(input);
+    let mut starved_streets: Vec<StarvedStreet> = observer
+        .stranded_cars
+        .into_iter()
+        .map(|(street_id, cars)| {
+            let lost_upper_bound = cars
+                .iter()
+                .filter(|&&car_id| min_travel_times[car_id] <= simulation_duration)
+                .map(|&car_id| input.header.bonus + simulation_duration - min_travel_times[car_id])
+                .sum();
+            StarvedStreet {
+                street_id,
+                cars,
+                lost_upper_bound,
+            }
+        })
+        .collect();
+    starved_streets.sort_by_key(|starved_street| starved_street.street_id);
+    (score, starved_streets)
+}
+
+pub fn warn_starvation(
+    output_file_path: &str,
+    input: &PInputData,
+    starved_streets: &[StarvedStreet],
+) {
+    if starved_streets.is_empty() {
+        return;
+    }
+    let stranded_cars: usize = starved_streets.iter().map(|s| s.cars.len()).sum();
+    let lost_upper_bound: Score = starved_streets.iter().map(|s| s.lost_upper_bound).sum();
+    eprintln!(
+        "warning: {}: {} cars stranded on {} streets that are never green, up to {} points lost",
+        output_file_path,
+        stranded_cars.to_formatted_string(&Locale::en),
+        starved_streets.len().to_formatted_string(&Locale::en),
+        lost_upper_bound.to_formatted_string(&Locale::en)
+    );
+    for starved_street in starved_streets {
+        let cars: Vec<String> = starved_street
+            .cars
+            .iter()
+            .map(|car_id| car_id.to_string())
+            .collect();
+        eprintln!(
+            "  {} (intersection {}): cars {}, up to {} points",
+            input.body.streets[starved_street.street_id].street_name,
+            input.body.streets[starved_street.street_id].intersection_end,
+            cars.join(" "),
+            starved_street
+                .lost_upper_bound
+                .to_formatted_string(&Locale::en)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::test_data;
+    use crate::score::Simulator;
+    use crate::starvation::{score_with_starvation, StarvedStreet};
+
+    #[test]
+    fn test_no_starvation_example() {
+        let input_data = test_data::get_example_input_data();
+        let output_data = test_data::get_example_output_data();
+        let (score, starved_streets) =
+            score_with_starvation(&Simulator::new(&input_data), &input_data, &output_data);
+        assert_eq!(score, 1002);
+        assert!(starved_streets.is_empty());
+    }
+
+    #[test]
+    fn test_starvation_example() {
+        let input_data = test_data::get_example_input_data();
+        let mut output_data = test_data::get_example_output_data();
+        // rue-de-moscou is no longer scheduled
+        output_data.intersection_schedules.pop();
+        output_data.schedules = 2;
+        let (score, starved_streets) =
+            score_with_starvation(&Simulator::new(&input_data), &input_data, &output_data);
+        assert_eq!(score, 0);
+        assert_eq!(
+            starved_streets,
+            vec![StarvedStreet {
+                street_id: 4,
+                cars: vec![1, 0],
+                lost_upper_bound: 2002,
+            }]
+        );
+    }
+}
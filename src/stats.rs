@@ -0,0 +1,329 @@
+use crate::analysis::{min_travel_times, street_usage, unreachable_cars};
+use crate::data::PInputData;
+use crate::score::{Score, Time};
+use num_format::{Locale, ToFormattedString};
+use std::fmt::Write as FmtWrite;
+
+const MOST_USED_STREETS: usize = 10;
+
+#[derive(Debug, PartialEq, Default)]
+pub struct Distribution {
+    pub min: usize,
+    // upper median for an even number of values
+    pub median: usize,
+    pub mean: f64,
+    pub max: usize,
+}
+
+impl Distribution {
+    fn of(mut values: Vec<usize>) -> Self {
+        if values.is_empty() {
+            return Distribution::default();
+        }
+        values.sort_unstable();
+        Distribution {
+            min: values[0],
+            median: values[values.len() / 2],
+            mean: values.iter().sum::<usize>() as f64 / values.len() as f64,
+            max: values[values.len() - 1],
+        }
+    }
+
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"min\": {}, \"median\": {}, \"mean\": {:.2}, \"max\": {}}}",
+            self.min, self.median, self.mean, self.max
+        )
+    }
+}
+
+impl std::fmt::Display for Distribution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "min {}, median {}, mean {:.2}, max {}",
+            self.min.to_formatted_string(&Locale::en),
+            self.median.to_formatted_string(&Locale::en),
+            self.mean,
+            self.max.to_formatted_string(&Locale::en)
+        )
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InputStats {
+    pub simulation_duration: Time,
+    pub intersections: usize,
+    pub streets: usize,
+    pub cars: usize,
+    pub bonus: Score,
+    pub street_lengths: Distribution,
+    // number of streets of each car path
+    pub path_streets: Distribution,
+    // cf `min_travel_times`
+    pub path_travel_times: Distribution,
+    // intersections with 1, 2 and 3 or more incoming streets
+    pub incoming_streets: [usize; 3],
+    // (street name, cars) most used first
+    pub most_used_streets: Vec<(String, usize)>,
+    pub unused_streets: usize,
+    pub unreachable_cars: usize,
+    // bonus of cars that cannot finish whatever the schedule
+    pub unreachable_bonus: Score,
+    // score if no car ever waits at a light
+    pub upper_bound: Score,
+}
+
+pub fn input_stats(input: &PInputData) -> InputStats {
+    let simulation_duration = input.header.simulation_duration;
+    let bonus = input.header.bonus;
+    let min_travel_times = min_travel_times(input);
+    let unreachable_cars = unreachable_cars(input).len();
+    let upper_bound = min_travel_times
+        .iter()
+        .filter(|&&min_travel_time| min_travel_time <= simulation_duration)
+        .map(|&min_travel_time| bonus + simulation_duration - min_travel_time)
+        .sum();
+
+    let mut incoming = vec![0usize; input.header.intersections];
+    for street in &input.body.streets {
+        incoming[street.intersection_end] += 1;
+    }
+    let mut incoming_streets = [0; 3];
+    for &streets in incoming.iter().filter(|&&streets| streets > 0) {
+        incoming_streets[streets.min(3) - 1] += 1;
+    }
+
+    let usage = street_usage(input);
+    let mut street_ids: Vec<usize> = (0..usage.len()).collect();
+    street_ids.sort_by_key(|&street_id| (std::cmp::Reverse(usage[street_id]), street_id));
+    let most_used_streets = street_ids
+        .iter()
+        .take(MOST_USED_STREETS)
+        .filter(|&&street_id| usage[street_id] > 0)
+        .map(|&street_id| {
+            (
+                input.body.streets[street_id].street_name.clone(),
+                usage[street_id],
+            )
+        })
+        .collect();
+
+    InputStats {
+        simulation_duration,
+        intersections: input.header.intersections,
+        streets: input.header.streets,
+        cars: input.body.car_paths.len(),
+        bonus,
+        street_lengths: Distribution::of(
+            input
+                .body
+                .streets
+                .iter()
+                .map(|street| street.street_length)
+                .collect(),
+        ),
+        path_streets: Distribution::of(
+            input
+                .body
+                .car_paths
+                .iter()
+                .map(|car_path| car_path.street_names.len())
+                .collect(),
+        ),
+        path_travel_times: Distribution::of(min_travel_times),
+        incoming_streets,
+        most_used_streets,
+        unused_streets: usage.iter().filter(|&&cars| cars == 0).count(),
+        unreachable_cars,
+        unreachable_bonus: unreachable_cars * bonus,
+        upper_bound,
+    }
+}
+
+pub fn print_stats(input_file_path: &str, stats: &InputStats) {
+    println!("{}", input_file_path);
+    println!(
+        "  duration: {}s, intersections: {}, streets: {}, cars: {}, bonus: {}",
+        stats.simulation_duration.to_formatted_string(&Locale::en),
+        stats.intersections.to_formatted_string(&Locale::en),
+        stats.streets.to_formatted_string(&Locale::en),
+        stats.cars.to_formatted_string(&Locale::en),
+        stats.bonus.to_formatted_string(&Locale::en)
+    );
+    println!("  street lengths: {}", stats.street_lengths);
+    println!("  streets per path: {}", stats.path_streets);
+    println!("  path travel times: {}", stats.path_travel_times);
+    println!(
+        "  intersections with 1 / 2 / 3+ incoming streets: {} / {} / {}",
+        stats.incoming_streets[0].to_formatted_string(&Locale::en),
+        stats.incoming_streets[1].to_formatted_string(&Locale::en),
+        stats.incoming_streets[2].to_formatted_string(&Locale::en)
+    );
+    println!(
+        "  unused streets: {}",
+        stats.unused_streets.to_formatted_string(&Locale::en)
+    );
+    println!("  most used streets:");
+    for (street_name, cars) in &stats.most_used_streets {
+        println!(
+            "    {}: {} cars",
+            street_name,
+            cars.to_formatted_string(&Locale::en)
+        );
+    }
+    println!(
+        "  unreachable cars: {} / {}",
+        stats.unreachable_cars.to_formatted_string(&Locale::en),
+        stats.cars.to_formatted_string(&Locale::en)
+    );
+    println!(
+        "  bonus out of reach: {}",
+        stats.unreachable_bonus.to_formatted_string(&Locale::en)
+    );
+    println!(
+        "  upper bound: {}",
+        stats.upper_bound.to_formatted_string(&Locale::en)
+    );
+}
+
+fn escape_json(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            c if c < '\u{20}' => write!(escaped, "\\u{:04x}", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// One line JSON object, numbers are not formatted.
+pub fn stats_json(input_file_path: &str, stats: &InputStats) -> String {
+    let most_used_streets: Vec<String> = stats
+        .most_used_streets
+        .iter()
+        .map(|(street_name, cars)| {
+            format!(
+                "{{\"street\": \"{}\", \"cars\": {}}}",
+                escape_json(street_name),
+                cars
+            )
+        })
+        .collect();
+    let mut s = String::new();
+    write!(
+        s,
+        "{{\"file\": \"{}\", \"simulation_duration\": {}, \"intersections\": {}, \"streets\": {}, \"cars\": {}, \"bonus\": {}, ",
+        escape_json(input_file_path),
+        stats.simulation_duration,
+        stats.intersections,
+        stats.streets,
+        stats.cars,
+        stats.bonus
+    )
+    .unwrap();
+    write!(
+        s,
+        "\"street_lengths\": {}, \"path_streets\": {}, \"path_travel_times\": {}, ",
+        stats.street_lengths.to_json(),
+        stats.path_streets.to_json(),
+        stats.path_travel_times.to_json()
+    )
+    .unwrap();
+    write!(
+        s,
+        "\"incoming_streets\": {{\"1\": {}, \"2\": {}, \"3+\": {}}}, \"unused_streets\": {}, \"most_used_streets\": [{}], ",
+        stats.incoming_streets[0],
+        stats.incoming_streets[1],
+        stats.incoming_streets[2],
+        stats.unused_streets,
+        most_used_streets.join(", ")
+    )
+    .unwrap();
+    write!(
+        s,
+        "\"unreachable_cars\": {}, \"unreachable_bonus\": {}, \"upper_bound\": {}}}",
+        stats.unreachable_cars, stats.unreachable_bonus, stats.upper_bound
+    )
+    .unwrap();
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::test_data;
+    use crate::stats::{escape_json, input_stats, stats_json, Distribution, InputStats};
+
+    #[test]
+    fn test_input_stats_example() {
+        let mut input_data = test_data::get_example_input_data();
+        assert_eq!(input_stats(&input_data).upper_bound, 2002);
+        input_data.header.simulation_duration = 5;
+        assert_eq!(
+            input_stats(&input_data),
+            InputStats {
+                simulation_duration: 5,
+                intersections: 4,
+                streets: 5,
+                cars: 2,
+                bonus: 1000,
+                street_lengths: Distribution {
+                    min: 1,
+                    median: 1,
+                    mean: 1.6,
+                    max: 3,
+                },
+                path_streets: Distribution {
+                    min: 3,
+                    median: 4,
+                    mean: 3.5,
+                    max: 4,
+                },
+                path_travel_times: Distribution {
+                    min: 4,
+                    median: 6,
+                    mean: 5.0,
+                    max: 6,
+                },
+                incoming_streets: [3, 1, 0],
+                most_used_streets: vec![
+                    ("rue-de-londres".to_string(), 2),
+                    ("rue-de-moscou".to_string(), 2),
+                    ("rue-d-amsterdam".to_string(), 1),
+                    ("rue-d-athenes".to_string(), 1),
+                    ("rue-de-rome".to_string(), 1),
+                ],
+                unused_streets: 0,
+                unreachable_cars: 1,
+                unreachable_bonus: 1000,
+                upper_bound: 1001,
+            }
+        );
+    }
+
+    #[test]
+    fn test_stats_json_example() {
+        let input_data = test_data::get_example_input_data();
+        let json = stats_json("a.txt", &input_stats(&input_data));
+        assert!(json.starts_with("{\"file\": \"a.txt\", \"simulation_duration\": 6,"));
+        assert!(json.contains(
+            "\"street_lengths\": {\"min\": 1, \"median\": 1, \"mean\": 1.60, \"max\": 3}"
+        ));
+        assert!(json.contains("\"incoming_streets\": {\"1\": 3, \"2\": 1, \"3+\": 0}"));
+        assert!(json.ends_with("\"upper_bound\": 2002}"));
+    }
+
+    #[test]
+    fn test_escape_json() {
+        assert_eq!(escape_json("rue-de-rome"), "rue-de-rome");
+        assert_eq!(
+            escape_json("a\"b\\c\nd\te\r\u{1}"),
+            "a\\\"b\\\\c\\nd\\te\\u000d\\u0001"
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use crate::data::{PInputData, POutputData};
+use crate::score::{CarId, Observer, Score, Simulator, Time};
+use num_format::{Locale, ToFormattedString};
+
+#[derive(Debug, PartialEq)]
+pub struct SweepRow {
+    pub simulation_duration: Time,
+    pub score: Score,
+    pub finished_cars: usize,
+}
+
+#[derive(Default)]
+struct FinishTimesObserver {
+    finish_times: Vec<Time>,
+}
+
+impl Observer for FinishTimesObserver {
+    fn on_finished(&mut self, time: Time, _car_id: CarId, _points: Score) {
+        self.finish_times.push(time);
+    }
+}
+
+/// Scores `output` for each of `durations` from a single simulation.
+///
+/// Cars move the same way whatever the simulation duration, so the simulation runs
+/// once for the longest duration and each row only counts the cars finished in time.
+pub fn duration_sweep(
+    input: &PInputData,
+    output: &POutputData,
+    durations: &[Time],
+) -> Vec<SweepRow> {
+    let max_duration = durations.iter().copied().max().unwrap_or(0);
+    let mut observer = FinishTimesObserver::default();
+    Simulator::new(input)
+        .with_simulation_duration(max_duration)
+        .simulate(output, &mut observer);
+    // finish times are reported in increasing order
+    let finish_times = observer.finish_times;
+    let mut prefix_sums: Vec<Time> = vec![0; finish_times.len() + 1];
+    for (i, finish_time) in finish_times.iter().enumerate() {
+        prefix_sums[i + 1] = prefix_sums[i] + finish_time;
+    }
+    durations
+        .iter()
+        .map(|&simulation_duration| {
+            let finished_cars = finish_times.partition_point(|&t| t <= simulation_duration);
+            SweepRow {
+                simulation_duration,
+                score: finished_cars * (input.header.bonus + simulation_duration)
+                    - prefix_sums[finished_cars],
+                finished_cars,
+            }
+        })
+        .collect()
+}
+
+pub fn print_sweep(input: &PInputData, rows: &[SweepRow]) {
+    println!("duration score finished cars");
+    for row in rows {
+        let marker = if row.simulation_duration == input.header.simulation_duration {
+            " (input)"
+        } else {
+            ""
+        };
+        println!(
+            "{} {} {} / {}{}",
+            row.simulation_duration.to_formatted_string(&Locale::en),
+            row.score.to_formatted_string(&Locale::en),
+            row.finished_cars.to_formatted_string(&Locale::en),
+            input.body.car_paths.len().to_formatted_string(&Locale::en),
+            marker
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::test_data;
+    use crate::score::compute_score;
+    use crate::sweep::{duration_sweep, SweepRow};
+
+    #[test]
+    fn test_duration_sweep_example() {
+        let mut input_data = test_data::get_example_input_data();
+        let output_data = test_data::get_example_output_data();
+        let rows = duration_sweep(&input_data, &output_data, &[3, 4, 6, 7, 10]);
+        assert_eq!(
+            rows[1],
+            SweepRow {
+                simulation_duration: 4,
+                score: 1000,
+                finished_cars: 1,
+            }
+        );
+        for row in rows {
+            input_data.header.simulation_duration = row.simulation_duration;
+            assert_eq!(row.score, compute_score(&input_data, &output_data));
+        }
+    }
+}
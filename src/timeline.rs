@@ -0,0 +1,83 @@
+use crate::data::{PInputData, POutputData};
+use crate::report::simulate_timeline;
+use crate::score::Time;
+use std::fmt::Write as FmtWrite;
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// One row per bucket of `bucket_size` seconds with, for each named output, the score
+/// and the number of cars finished by the end of the bucket.
+///
+/// `time` is the end of the bucket, the last bucket ends with the simulation.
+pub fn timeline_csv(
+    input: &PInputData,
+    outputs: &[(&str, POutputData)],
+    bucket_size: Time,
+) -> String {
+    let timelines: Vec<_> = outputs
+        .iter()
+        .map(|(_, output)| simulate_timeline(input, output, bucket_size))
+        .collect();
+    let mut csv = String::from("time");
+    for (name, _) in outputs {
+        write!(
+            csv,
+            ",{},{}",
+            csv_field(&format!("{} score", name)),
+            csv_field(&format!("{} finished", name))
+        )
+        .unwrap();
+    }
+    csv.push('\n');
+    let simulation_duration = input.header.simulation_duration;
+    let buckets = timelines.first().map_or(0, |timeline| timeline.score.len());
+    for bucket in 0..buckets {
+        write!(
+            csv,
+            "{}",
+            ((bucket + 1) * bucket_size).min(simulation_duration)
+        )
+        .unwrap();
+        for timeline in &timelines {
+            write!(
+                csv,
+                ",{},{}",
+                timeline.score[bucket], timeline.finished[bucket]
+            )
+            .unwrap();
+        }
+        csv.push('\n');
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::test_data;
+    use crate::timeline::timeline_csv;
+
+    #[test]
+    fn test_timeline_csv_example() {
+        let input_data = test_data::get_example_input_data();
+        let output_data = test_data::get_example_output_data();
+        let mut never_green = test_data::get_example_output_data();
+        never_green.intersection_schedules.pop();
+        never_green.schedules = 2;
+        assert_eq!(
+            timeline_csv(
+                &input_data,
+                &[("example", output_data), ("a,b", never_green)],
+                4
+            ),
+            "time,example score,example finished,\"a,b score\",\"a,b finished\"\n\
+             4,1002,1,0,0\n\
+             6,1002,1,0,0\n"
+        );
+    }
+}
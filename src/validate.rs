@@ -0,0 +1,89 @@
+use crate::data::{PInputData, POutputData};
+use ahash::{AHashMap, AHashSet};
+
+/// Checks `output` against the rules of the problem statement the parser cannot check.
+///
+/// Returns one message per error, an empty list for a valid submission.
+pub fn validate_output(input: &PInputData, output: &POutputData) -> Vec<String> {
+    let street_of_name = input
+        .body
+        .streets
+        .iter()
+        .map(|street| (street.street_name.as_str(), street))
+        .collect::<AHashMap<&str, _>>();
+    let simulation_duration = input.header.simulation_duration;
+    let mut errors = vec![];
+    let mut scheduled_intersections = AHashSet::default();
+    for intersection_schedule in &output.intersection_schedules {
+        let intersection_id = intersection_schedule.intersection_id;
+        if intersection_id >= input.header.intersections {
+            errors.push(format!(
+                "intersection {}: unknown intersection, there are {} intersections",
+                intersection_id, input.header.intersections
+            ));
+        }
+        if !scheduled_intersections.insert(intersection_id) {
+            errors.push(format!(
+                "intersection {}: scheduled more than once",
+                intersection_id
+            ));
+        }
+        if intersection_schedule.light_schedules.is_empty() {
+            errors.push(format!(
+                "intersection {}: no incoming street",
+                intersection_id
+            ));
+        }
+        let mut scheduled_streets = AHashSet::default();
+        for (street_name, duration) in &intersection_schedule.light_schedules {
+            match street_of_name.get(street_name.as_str()) {
+                None => errors.push(format!(
+                    "intersection {}: unknown street {}",
+                    intersection_id, street_name
+                )),
+                Some(street) if street.intersection_end != intersection_id => errors.push(format!(
+                    "intersection {}: street {} ends at intersection {}",
+                    intersection_id, street_name, street.intersection_end
+                )),
+                _ => (),
+            }
+            if !scheduled_streets.insert(street_name.as_str()) {
+                errors.push(format!(
+                    "intersection {}: street {} scheduled more than once",
+                    intersection_id, street_name
+                ));
+            }
+            if *duration < 1 || *duration > simulation_duration {
+                errors.push(format!(
+                    "intersection {}: street {} green for {}s, expected 1 to {}s",
+                    intersection_id, street_name, duration, simulation_duration
+                ));
+            }
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::test_data;
+    use crate::validate::validate_output;
+
+    #[test]
+    fn test_validate_output_example() {
+        let input_data = test_data::get_example_input_data();
+        let mut output_data = test_data::get_example_output_data();
+        assert!(validate_output(&input_data, &output_data).is_empty());
+        output_data.intersection_schedules[0].light_schedules[1] = ("rue-de-rome".to_string(), 0);
+        output_data.intersection_schedules[2].intersection_id = 1;
+        assert_eq!(
+            validate_output(&input_data, &output_data),
+            vec![
+                "intersection 1: street rue-de-rome ends at intersection 3",
+                "intersection 1: street rue-de-rome green for 0s, expected 1 to 6s",
+                "intersection 1: scheduled more than once",
+                "intersection 1: street rue-de-moscou ends at intersection 2",
+            ]
+        );
+    }
+}
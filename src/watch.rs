@@ -0,0 +1,123 @@
+use crate::data::PInputData;
+use crate::parser::{parse_input_file, parse_output_file};
+use crate::report::format_signed;
+use crate::score::{compute_score, Score};
+use crate::validate::validate_output;
+use log::info;
+use num_format::{Locale, ToFormattedString};
+use std::fs::metadata;
+use std::thread::sleep;
+use std::time::{Duration, SystemTime};
+
+// cheap change detection: no OS-specific notification required
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct FileStamp {
+    modified: SystemTime,
+    len: u64,
+}
+
+fn file_stamp(path: &str) -> Option<FileStamp> {
+    let metadata = metadata(path).ok()?;
+    Some(FileStamp {
+        modified: metadata.modified().ok()?,
+        len: metadata.len(),
+    })
+}
+
+struct WatchedOutput<'a> {
+    input_index: usize,
+    output_file_path: &'a str,
+    stamp: Option<FileStamp>,
+    score: Option<Score>,
+}
+
+fn format_delta(previous: Option<Score>, current: Score) -> String {
+    match previous {
+        Some(previous) => format!(" ({})", format_signed(current as i64 - previous as i64)),
+        None => String::new(),
+    }
+}
+
+/// Re-scores output files each time they change, until the process is killed.
+///
+/// Input files are parsed once and kept in memory.
+pub fn watch(input_output_files: &[(&str, &str)], interval: Duration) -> anyhow::Result<()> {
+    let mut input_file_paths: Vec<&str> = vec![];
+    let mut inputs: Vec<PInputData> = vec![];
+    let mut watched_outputs: Vec<WatchedOutput> = vec![];
+    for &(input_file_path, output_file_path) in input_output_files {
+        // the same input may be scored against several outputs
+        let input_index = match input_file_paths.iter().position(|&p| p == input_file_path) {
+            Some(input_index) => input_index,
+            None => {
+                inputs.push(parse_input_file(input_file_path)?);
+                input_file_paths.push(input_file_path);
+                inputs.len() - 1
+            }
+        };
+        watched_outputs.push(WatchedOutput {
+            input_index,
+            output_file_path,
+            stamp: None,
+            score: None,
+        });
+    }
+    let many = watched_outputs.len() > 1;
+
+    loop {
+        let mut changed = false;
+        for watched_output in watched_outputs.iter_mut() {
+            let stamp = file_stamp(watched_output.output_file_path);
+            if stamp.is_none() || stamp == watched_output.stamp {
+                continue;
+            }
+            watched_output.stamp = stamp;
+            // the solver may still be writing the file, keep the last score until it parses
+            let output_data = match parse_output_file(watched_output.output_file_path) {
+                Ok(output_data) => output_data,
+                Err(e) => {
+                    eprintln!("{}: {}", watched_output.output_file_path, e);
+                    continue;
+                }
+            };
+            // an output naming unknown streets would make the simulation panic
+            let input_data = &inputs[watched_output.input_index];
+            let errors = validate_output(input_data, &output_data);
+            if !errors.is_empty() {
+                eprintln!("{}: {}", watched_output.output_file_path, errors.join(", "));
+                continue;
+            }
+            info!("scoring {}", watched_output.output_file_path);
+            let score = compute_score(input_data, &output_data);
+            println!(
+                "{} score: {}{}",
+                watched_output.output_file_path,
+                score.to_formatted_string(&Locale::en),
+                format_delta(watched_output.score, score)
+            );
+            watched_output.score = Some(score);
+            changed = true;
+        }
+        if many && changed {
+            let total_score: Score = watched_outputs.iter().filter_map(|w| w.score).sum();
+            println!(
+                "total score: {}",
+                total_score.to_formatted_string(&Locale::en)
+            );
+        }
+        sleep(interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::watch::format_delta;
+
+    #[test]
+    fn test_format_delta() {
+        assert_eq!(format_delta(None, 1002), "");
+        assert_eq!(format_delta(Some(2), 1002), " (+1,000)");
+        assert_eq!(format_delta(Some(1002), 2), " (-1,000)");
+        assert_eq!(format_delta(Some(2), 2), " (+0)");
+    }
+}
@@ -0,0 +1,39 @@
+use crate::data::POutputData;
+use std::fmt::Write as FmtWrite;
+use std::fs::{rename, write};
+
+/// Formats `output` using the submission file format.
+pub fn format_output(output: &POutputData) -> String {
+    let mut s = String::new();
+    writeln!(s, "{}", output.intersection_schedules.len()).unwrap();
+    for intersection_schedule in &output.intersection_schedules {
+        writeln!(s, "{}", intersection_schedule.intersection_id).unwrap();
+        writeln!(s, "{}", intersection_schedule.light_schedules.len()).unwrap();
+        for (street_name, duration) in &intersection_schedule.light_schedules {
+            writeln!(s, "{} {}", street_name, duration).unwrap();
+        }
+    }
+    s
+}
+
+/// Writes `output` to a temporary file first so that `path` is never left half-written.
+pub fn write_output_file(path: &str, output: &POutputData) -> anyhow::Result<()> {
+    let tmp_path = format!("{}.tmp", path);
+    write(&tmp_path, format_output(output))?;
+    rename(&tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::test_data;
+    use crate::parser::parse_output;
+    use crate::writer::format_output;
+
+    #[test]
+    fn test_format_output_round_trip() {
+        let output_data = test_data::get_example_output_data();
+        let s = format_output(&output_data);
+        assert_eq!(parse_output(&s).unwrap(), output_data);
+    }
+}